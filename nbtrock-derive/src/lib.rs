@@ -0,0 +1,252 @@
+//! `nbtrock`的派生宏, 见`nbtrock::NbtCompound`
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, GenericArgument,
+    LitStr, PathArguments, Type,
+};
+
+#[proc_macro_derive(NbtCompound, attributes(nbt))]
+pub fn derive_nbt_compound(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum DefaultValue {
+    None,
+    Trait,
+    Path(syn::ExprPath),
+}
+
+struct Attrs {
+    rename: Option<String>,
+    tag: Option<u8>,
+    default: DefaultValue,
+    flatten: bool,
+    unknown: bool,
+}
+
+fn tag_of(name: &str) -> Option<u8> {
+    Some(match name {
+        "byte" => 0x01,
+        "short" => 0x02,
+        "int" => 0x03,
+        "long" => 0x04,
+        "float" => 0x05,
+        "double" => 0x06,
+        "byte_array" => 0x07,
+        "string" => 0x08,
+        "int_array" => 0x0b,
+        "long_array" => 0x0c,
+        _ => return None,
+    })
+}
+
+fn parse_attrs(field: &syn::Field) -> syn::Result<Attrs> {
+    let mut attrs = Attrs {
+        rename: None,
+        tag: None,
+        default: DefaultValue::None,
+        flatten: false,
+        unknown: false,
+    };
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("nbt")) {
+        attr.parse_nested_meta(|meta| {
+            let ident = meta
+                .path
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            match ident.as_str() {
+                "rename" => attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "default" => {
+                    attrs.default = if meta.input.peek(syn::Token![=]) {
+                        DefaultValue::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        DefaultValue::Trait
+                    }
+                }
+                "flatten" => attrs.flatten = true,
+                "unknown" => attrs.unknown = true,
+                name => match tag_of(name) {
+                    Some(tag) if attrs.tag.is_none() => attrs.tag = Some(tag),
+                    Some(_) => return Err(meta.error("只能指定一个标签类型")),
+                    None => return Err(meta.error("未知的nbt属性")),
+                },
+            }
+            Ok(())
+        })?;
+    }
+    if (attrs.flatten || attrs.unknown)
+        && (attrs.rename.is_some()
+            || attrs.tag.is_some()
+            || !matches!(attrs.default, DefaultValue::None))
+    {
+        return Err(syn::Error::new(
+            field.span(),
+            "flatten与unknown字段不能同时使用其他属性",
+        ));
+    }
+    if attrs.flatten && attrs.unknown {
+        return Err(syn::Error::new(
+            field.span(),
+            "flatten与unknown不能同时使用",
+        ));
+    }
+    Ok(attrs)
+}
+
+///如果类型为`Option<T>`则返回`T`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(input.span(), "NbtCompound只能用于结构体"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            input.span(),
+            "NbtCompound只能用于具有命名字段的结构体",
+        ));
+    };
+
+    let krate = quote!(::nbtrock);
+    let private = quote!(#krate::__private);
+    let mut reads = Vec::new();
+    let mut flatten_reads = Vec::new();
+    let mut unknown_read = None;
+    let mut writes = Vec::new();
+    let mut checks = Vec::new();
+    let mut names = Vec::new();
+
+    for field in &fields.named {
+        let attrs = parse_attrs(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        names.push(ident);
+
+        if attrs.unknown {
+            if unknown_read.is_some() {
+                return Err(syn::Error::new(field.span(), "只能有一个unknown字段"));
+            }
+            unknown_read = Some(quote! {
+                let #ident = ::std::mem::take(map);
+            });
+            writes.push(quote! {
+                for (k, v) in &self.#ident {
+                    map.insert(k.clone(), v.clone());
+                }
+            });
+            continue;
+        }
+
+        if attrs.flatten {
+            flatten_reads.push(quote! {
+                let #ident = <#ty as #krate::NbtCompound>::read_compound(map)?;
+            });
+            writes.push(quote! {
+                #krate::NbtCompound::write_compound(&self.#ident, map);
+            });
+            continue;
+        }
+
+        let key = attrs.rename.unwrap_or_else(|| ident.unraw().to_string());
+        let optional = option_inner(ty);
+        let inner = optional.unwrap_or(ty);
+        if optional.is_some() && !matches!(attrs.default, DefaultValue::None) {
+            return Err(syn::Error::new(field.span(), "Option字段不能使用default"));
+        }
+
+        if let Some(tag) = attrs.tag {
+            checks.push(quote_spanned! {inner.span()=>
+                const _: () = #private::TagCheck::<#inner, #tag>::OK;
+            });
+        }
+
+        let read = match attrs.tag {
+            Some(tag) => quote!(#private::read_tagged::<#inner>(map, #key, #tag)?),
+            None => quote!(#private::read::<#inner>(map, #key)?),
+        };
+        let read = match &attrs.default {
+            DefaultValue::Trait => quote!(#read.unwrap_or_default()),
+            DefaultValue::Path(path) => quote!(#read.unwrap_or_else(#path)),
+            DefaultValue::None if optional.is_some() => read,
+            DefaultValue::None => quote!(#read.ok_or_else(|| #private::missing(#key))?),
+        };
+        reads.push(quote! {
+            let #ident = #read;
+        });
+
+        let value = |v: TokenStream2| match attrs.tag {
+            Some(tag) => quote!(#private::write_tagged::<#inner, #tag>(#v)),
+            None => quote!(#krate::NbtValue::to_value(#v)),
+        };
+        writes.push(if optional.is_some() {
+            let value = value(quote!(v));
+            quote! {
+                if let ::std::option::Option::Some(v) = &self.#ident {
+                    map.insert(::std::string::String::from(#key), #value);
+                }
+            }
+        } else {
+            let value = value(quote!(&self.#ident));
+            quote! {
+                map.insert(::std::string::String::from(#key), #value);
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    //结构体没有泛型参数时, 在`cargo check`阶段就能报告无法无损保存的标签类型
+    if !input.generics.params.is_empty() {
+        checks.clear();
+    }
+
+    Ok(quote! {
+        #(#checks)*
+
+        impl #impl_generics #krate::NbtCompound for #name #ty_generics #where_clause {
+            fn read_compound(
+                map: &mut #krate::Map<::std::string::String, #krate::Value>,
+            ) -> #krate::IResult<Self> {
+                #(#reads)*
+                #(#flatten_reads)*
+                #unknown_read
+                ::std::result::Result::Ok(Self { #(#names),* })
+            }
+
+            fn write_compound(&self, map: &mut #krate::Map<::std::string::String, #krate::Value>) {
+                #(#writes)*
+            }
+        }
+
+        impl #impl_generics #krate::NbtValue for #name #ty_generics #where_clause {
+            const TAG: u8 = 0x0a;
+
+            fn from_value(v: #krate::Value) -> #krate::IResult<Self> {
+                <Self as #krate::NbtCompound>::from_compound(#private::compound(v)?)
+            }
+
+            fn to_value(&self) -> #krate::Value {
+                #krate::Value::Compound(#krate::NbtCompound::to_compound(self))
+            }
+        }
+    })
+}
@@ -0,0 +1,280 @@
+//! Rust类型与NBT标签之间的映射, 供`#[derive(NbtCompound)]`使用
+use crate::{Error, IResult, Map, Value};
+
+///可以与单个NBT标签相互转换的类型
+pub trait NbtValue: Sized {
+    ///对应的标签类型ID, 为0时表示任意类型
+    const TAG: u8;
+    ///`TAG_List`的元素类型ID, 不是列表或元素为任意类型时为0
+    const ELEMENT: u8 = 0;
+
+    fn from_value(v: Value) -> IResult<Self>;
+
+    fn to_value(&self) -> Value;
+}
+
+///可以与`TAG_Compound`相互转换的类型
+///
+///`#[derive(NbtCompound)]`(需要`derive`功能)会同时实现[`NbtCompound`]与[`NbtValue`],
+///键名默认为字段名, `r#type`等原始标识符的键名为`type`. 可用的字段属性:
+///
+/// - `#[nbt(rename = "Name")]`: 使用指定的键名
+/// - `#[nbt(byte)]`, `#[nbt(short)]`, `#[nbt(int)]`, `#[nbt(long)]`, `#[nbt(float)]`,
+///   `#[nbt(double)]`, `#[nbt(byte_array)]`, `#[nbt(int_array)]`, `#[nbt(long_array)]`:
+///   以指定的标签类型保存, 只能指定能够无损保存字段所有值的类型, 例如将`bool`或`i8`字段保存为
+///   `TAG_Int`, 将`Vec<i32>`保存为`TAG_Int_Array`. 可能损失精度的类型(如`i32`与`byte`,
+///   `String`与`byte`)无法通过编译, 读取时超出字段类型范围的值会返回[`Error::Range`]
+/// - `#[nbt(default)]`, `#[nbt(default = "path")]`: 键不存在时使用默认值
+/// - `#[nbt(flatten)]`: 字段本身实现了[`NbtCompound`], 其键与外层处于同一层级
+/// - `#[nbt(unknown)]`: 类型为`Map<String, Value>`, 保存所有未被其他字段读取的键,
+///   写入时原样写回
+///
+///类型为`Option<T>`的字段是可选的, 为`None`时不会写入
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(NbtCompound)]
+/// struct Item {
+///     #[nbt(rename = "Name")]
+///     name: String,
+///     #[nbt(rename = "Count")]
+///     count: i8,
+///     #[nbt(rename = "WasPickedUp", byte)]
+///     picked_up: bool,
+///     #[nbt(unknown)]
+///     other: Map<String, Value>,
+/// }
+/// ```
+pub trait NbtCompound: Sized {
+    ///从`map`中读取字段, 已读取的键会从`map`中移除
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self>;
+
+    ///将字段写入`map`
+    fn write_compound(&self, map: &mut Map<String, Value>);
+
+    fn from_compound(mut map: Map<String, Value>) -> IResult<Self> {
+        Self::read_compound(&mut map)
+    }
+
+    fn to_compound(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        self.write_compound(&mut map);
+        map
+    }
+}
+
+macro_rules! impl_nbt_value {
+    ($($t:ty => $variant:ident = $tag:literal),* $(,)?) => {
+        $(
+            impl NbtValue for $t {
+                const TAG: u8 = $tag;
+
+                fn from_value(v: Value) -> IResult<Self> {
+                    match v {
+                        Value::$variant(v) => Ok(v),
+                        v => Err(v.mismatch(Self::TAG)),
+                    }
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::$variant(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+impl_nbt_value! {
+    i8 => Byte = 0x01,
+    i16 => Short = 0x02,
+    i32 => Int = 0x03,
+    i64 => Long = 0x04,
+    f32 => Float = 0x05,
+    f64 => Double = 0x06,
+    String => String = 0x08,
+    Map<String, Value> => Compound = 0x0a,
+}
+
+impl NbtValue for bool {
+    const TAG: u8 = 0x01;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        match v {
+            Value::Byte(v) => Ok(v != 0),
+            v => Err(v.mismatch(Self::TAG)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Byte(*self as i8)
+    }
+}
+
+impl NbtValue for Value {
+    const TAG: u8 = 0;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Ok(v)
+    }
+
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl<T: NbtValue> NbtValue for Vec<T> {
+    const TAG: u8 = 0x09;
+    const ELEMENT: u8 = T::TAG;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        match v {
+            Value::List(v) => v
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| T::from_value(v).map_err(|e| e.at(&i.to_string())))
+                .collect(),
+            v => Err(v.mismatch(Self::TAG)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(NbtValue::to_value).collect())
+    }
+}
+
+impl<T: NbtValue, const N: usize> NbtValue for [T; N] {
+    const TAG: u8 = 0x09;
+    const ELEMENT: u8 = T::TAG;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        let v = Vec::<T>::from_value(v)?;
+        let found = v.len();
+        v.try_into().map_err(|_| Error::Length {
+            expected: N,
+            found,
+            path: String::new(),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(NbtValue::to_value).collect())
+    }
+}
+
+///从`map`中取出键为`key`的值, 键不存在时返回[`Error::MissingKey`]
+pub(crate) fn take<T: NbtValue>(map: &mut Map<String, Value>, key: &str) -> IResult<T> {
+    __private::read(map, key)?.ok_or_else(|| __private::missing(key))
+}
+
+///将`v`以键`key`写入`map`
+pub(crate) fn put<T: NbtValue>(map: &mut Map<String, Value>, key: &str, v: &T) {
+    map.insert(key.to_owned(), v.to_value());
+}
+
+///按字节序重新排列键, 与基岩版保存时的顺序一致
+pub(crate) fn sort_keys(map: &mut Map<String, Value>) {
+    let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    map.extend(entries);
+}
+
+///`written`中不在`source`里的键, 即读取时缺少而写回时以默认值补全的键
+pub(crate) fn filled_keys(source: &[String], written: Map<String, Value>) -> Map<String, Value> {
+    written
+        .into_iter()
+        .filter(|(k, _)| !source.contains(k))
+        .collect()
+}
+
+///移除值仍与补全的默认值相同的键, 使读取时缺少的键写回时也保持缺少
+pub(crate) fn drop_filled(map: &mut Map<String, Value>, filled: &Map<String, Value>) {
+    for (k, v) in filled {
+        if map.get(k) == Some(v) {
+            map.remove(k);
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    pub fn read<T: NbtValue>(map: &mut Map<String, Value>, key: &str) -> IResult<Option<T>> {
+        map.remove(key)
+            .map(|v| T::from_value(v).map_err(|e| e.at(key)))
+            .transpose()
+    }
+
+    pub fn read_tagged<T: NbtValue>(
+        map: &mut Map<String, Value>,
+        key: &str,
+        tag: u8,
+    ) -> IResult<Option<T>> {
+        map.remove(key)
+            .map(|v| {
+                if v.tag() != tag {
+                    return Err(v.mismatch(tag));
+                }
+                let v = match (v.cast_exact(T::TAG)?, T::ELEMENT) {
+                    //数组转换为列表后元素还需要转换为字段的元素类型
+                    (Value::List(list), element) if element != 0 => Value::List(
+                        list.into_iter()
+                            .enumerate()
+                            .map(|(i, v)| v.cast_exact(element).map_err(|e| e.at(&i.to_string())))
+                            .collect::<IResult<_>>()?,
+                    ),
+                    (v, _) => v,
+                };
+                T::from_value(v)
+            })
+            .transpose()
+            .map_err(|e| e.at(key))
+    }
+
+    ///标签类型已由[`TagCheck`]检查, 转换不会失败也不会损失精度
+    pub fn write_tagged<T: NbtValue, const TAG: u8>(v: &T) -> Value {
+        let () = TagCheck::<T, TAG>::OK;
+        v.to_value().cast(TAG).expect("标签类型已在编译期检查")
+    }
+
+    ///标签类型`from`(元素类型为`element`)的所有值能否无损地转换为`to`,
+    ///整数可以转换为更大的整数或能精确表示它的浮点数, `TAG_List`可以转换为元素类型兼容的数组
+    pub const fn lossless(from: u8, element: u8, to: u8) -> bool {
+        const fn widen(from: u8, to: u8) -> bool {
+            from == to
+                || matches!(
+                    (from, to),
+                    (0x01, 0x02..=0x06) | (0x02, 0x03..=0x06) | (0x03, 0x04 | 0x06) | (0x05, 0x06)
+                )
+        }
+        match (from, to) {
+            (0, _) => false,
+            (0x09, 0x07) => widen(element, 0x01),
+            (0x09, 0x0b) => widen(element, 0x03),
+            (0x09, 0x0c) => widen(element, 0x04),
+            (from, to) => widen(from, to),
+        }
+    }
+
+    ///在编译期检查`#[nbt(byte)]`等属性能否无损地保存字段类型
+    pub struct TagCheck<T, const TAG: u8>(std::marker::PhantomData<T>);
+
+    impl<T: NbtValue, const TAG: u8> TagCheck<T, TAG> {
+        pub const OK: () = assert!(
+            lossless(T::TAG, T::ELEMENT, TAG),
+            "nbt属性指定的标签类型无法无损地保存字段类型"
+        );
+    }
+
+    pub fn compound(v: Value) -> IResult<Map<String, Value>> {
+        match v {
+            Value::Compound(map) => Ok(map),
+            v => Err(v.mismatch(0x0a)),
+        }
+    }
+
+    pub fn missing(key: &str) -> Error {
+        Error::MissingKey(key.to_owned())
+    }
+}
@@ -0,0 +1,429 @@
+//! [`Value`]与Rust类型之间的转换
+//!
+//! - `From<T> for Value`: 由Rust类型构造标签
+//! - `TryFrom<Value> for T`: 严格按照标签类型取出值, 类型不符时返回[`Error::Type`]
+//! - `as_*`: 严格按照标签类型借用值
+//! - `to_*`: 允许无损的数值拓宽, 例如`TAG_Byte`可以作为`i32`读取
+use crate::{Error, IResult, Map, Value};
+use std::ops::{Index, IndexMut};
+
+macro_rules! impl_from {
+    ($($t:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$t> for Value {
+                #[inline]
+                fn from(v: $t) -> Self {
+                    Value::$variant(v)
+                }
+            }
+
+            impl TryFrom<Value> for $t {
+                type Error = Error;
+
+                fn try_from(v: Value) -> IResult<Self> {
+                    match v {
+                        Value::$variant(v) => Ok(v),
+                        v => Err(v.mismatch(Value::$variant(Default::default()).tag())),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    i8 => Byte,
+    i16 => Short,
+    i32 => Int,
+    i64 => Long,
+    f32 => Float,
+    f64 => Double,
+    String => String,
+    Vec<Value> => List,
+    Map<String, Value> => Compound,
+    Vec<i8> => ByteArray,
+    Vec<i32> => IntArray,
+    Vec<i64> => LongArray,
+}
+
+///基岩版中布尔值以`TAG_Byte`保存
+impl From<bool> for Value {
+    #[inline]
+    fn from(v: bool) -> Self {
+        Value::Byte(v as i8)
+    }
+}
+
+impl From<&str> for Value {
+    #[inline]
+    fn from(v: &str) -> Self {
+        Value::String(v.to_owned())
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(v: Value) -> IResult<Self> {
+        match v {
+            Value::Byte(v) => Ok(v != 0),
+            v => Err(v.mismatch(0x01)),
+        }
+    }
+}
+
+///收集为`TAG_List`, 不检查元素类型是否一致
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Value::List(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<(String, Value)> for Value {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        Value::Compound(iter.into_iter().collect())
+    }
+}
+
+impl<'a> FromIterator<(&'a str, Value)> for Value {
+    fn from_iter<I: IntoIterator<Item = (&'a str, Value)>>(iter: I) -> Self {
+        Value::Compound(iter.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+}
+
+///按键取得`TAG_Compound`中的值, 不是`TAG_Compound`或键不存在时panic
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Compound(map) => map
+                .get(key)
+                .unwrap_or_else(|| panic!("键 \"{key}\" 不存在")),
+            v => panic!("无法以键 \"{key}\" 索引{}", v.tag_name()),
+        }
+    }
+}
+
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self {
+            Value::Compound(map) => map
+                .get_mut(key)
+                .unwrap_or_else(|| panic!("键 \"{key}\" 不存在")),
+            v => panic!("无法以键 \"{key}\" 索引{}", v.tag_name()),
+        }
+    }
+}
+
+///按下标取得`TAG_List`中的值, 不是`TAG_List`或越界时panic
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            Value::List(list) => &list[index],
+            v => panic!("无法以下标 {index} 索引{}", v.tag_name()),
+        }
+    }
+}
+
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        match self {
+            Value::List(list) => &mut list[index],
+            v => panic!("无法以下标 {index} 索引{}", v.tag_name()),
+        }
+    }
+}
+
+macro_rules! accessors {
+    ($($as:ident $(, $as_mut:ident)? => $variant:ident: $t:ty),* $(,)?) => {
+        $(
+            pub fn $as(&self) -> Option<&$t> {
+                match self {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            $(
+                pub fn $as_mut(&mut self) -> Option<&mut $t> {
+                    match self {
+                        Value::$variant(v) => Some(v),
+                        _ => None,
+                    }
+                }
+            )?
+        )*
+    };
+}
+
+macro_rules! widening {
+    ($($to:ident -> $t:ty: $($variant:ident)|*),* $(,)?) => {
+        $(
+            ///允许无损拓宽的数值读取
+            pub fn $to(&self) -> Option<$t> {
+                match *self {
+                    $(Value::$variant(v) => Some(v.into()),)*
+                    _ => None,
+                }
+            }
+        )*
+    };
+}
+
+impl Value {
+    pub fn as_byte(&self) -> Option<i8> {
+        match *self {
+            Value::Byte(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_short(&self) -> Option<i16> {
+        match *self {
+            Value::Short(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match *self {
+            Value::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_long(&self) -> Option<i64> {
+        match *self {
+            Value::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match *self {
+            Value::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_double(&self) -> Option<f64> {
+        match *self {
+            Value::Double(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_byte().map(|v| v != 0)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    accessors! {
+        as_string_mut => String: String,
+        as_list, as_list_mut => List: Vec<Value>,
+        as_compound, as_compound_mut => Compound: Map<String, Value>,
+        as_byte_array, as_byte_array_mut => ByteArray: Vec<i8>,
+        as_int_array, as_int_array_mut => IntArray: Vec<i32>,
+        as_long_array, as_long_array_mut => LongArray: Vec<i64>,
+    }
+
+    widening! {
+        to_i16 -> i16: Byte | Short,
+        to_i32 -> i32: Byte | Short | Int,
+        to_i64 -> i64: Byte | Short | Int | Long,
+        to_f32 -> f32: Byte | Short | Float,
+        to_f64 -> f64: Byte | Short | Int | Float | Double,
+    }
+
+    ///取得`TAG_Compound`中键为`key`的值
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_compound()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_compound_mut()?.get_mut(key)
+    }
+
+    ///按照以`/`分隔的路径取得值, `TAG_List`以下标作为路径,
+    ///例如`structure/palette/default/block_palette/0/name`
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('/')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |v, p| match v {
+                Value::Compound(map) => map.get(p),
+                Value::List(list) => list.get(p.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path.split('/')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |v, p| match v {
+                Value::Compound(map) => map.get_mut(p),
+                Value::List(list) => list.get_mut(p.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    ///按照路径严格地取出值, 错误中会包含路径
+    ///
+    /// ```
+    /// use nbtrock::{nbt, Error};
+    ///
+    /// let v = nbt!({ "size": [28, 50, 9], "format_version": 1i16 });
+    /// assert_eq!(v.get_as::<i32>("size/1").unwrap(), 50);
+    /// match v.get_as::<i32>("format_version") {
+    ///     Err(Error::Type { expected, found, path }) => {
+    ///         assert_eq!((expected, found, path.as_str()), ("TAG_Int", "TAG_Short", "format_version"))
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn get_as<T: TryFrom<Value, Error = Error>>(&self, path: &str) -> IResult<T> {
+        let v = self
+            .get_path(path)
+            .ok_or_else(|| Error::MissingKey(path.to_owned()))?;
+        T::try_from(v.clone()).map_err(|e| e.at(path))
+    }
+
+    ///按照路径读取数值, 允许无损拓宽, 例如`TAG_Byte`可以读取为`i64`
+    pub fn get_widened<T: TryFrom<Value, Error = Error>>(&self, path: &str) -> IResult<T> {
+        let v = self
+            .get_path(path)
+            .ok_or_else(|| Error::MissingKey(path.to_owned()))?;
+        T::try_from(v.clone().widen::<T>()).map_err(|e| e.at(path))
+    }
+
+    ///如果`T`是比当前数值更宽的数值类型, 则转换为`T`对应的标签
+    fn widen<T: TryFrom<Value, Error = Error>>(self) -> Value {
+        let widened = [
+            self.to_i16().map(Value::Short),
+            self.to_i32().map(Value::Int),
+            self.to_i64().map(Value::Long),
+            self.to_f32().map(Value::Float),
+            self.to_f64().map(Value::Double),
+        ];
+        widened
+            .into_iter()
+            .flatten()
+            .find(|v| T::try_from(v.clone()).is_ok())
+            .unwrap_or(self)
+    }
+
+    pub(crate) fn mismatch(&self, expected: u8) -> Error {
+        Error::Type {
+            expected: Value::name_of(expected),
+            found: self.tag_name(),
+            path: String::new(),
+        }
+    }
+
+    ///将数值或数组转换为`tag`类型的标签, 数值之间按照`as`的规则转换,
+    ///`TAG_List`与三种数组类型可以相互转换, `tag`为0时原样返回
+    pub fn cast(self, tag: u8) -> IResult<Value> {
+        if tag == 0 || self.tag() == tag {
+            return Ok(self);
+        }
+        let found = self.tag_name();
+        let mismatch = || Error::Type {
+            expected: Value::name_of(tag),
+            found,
+            path: String::new(),
+        };
+        let list = |v: Vec<Value>| -> IResult<Vec<Value>> {
+            v.into_iter()
+                .enumerate()
+                .map(|(i, v)| v.cast(element_tag(tag)).map_err(|e| e.at(&i.to_string())))
+                .collect()
+        };
+
+        Ok(match self {
+            Value::Byte(v) => numeric(v as f64, v as i64, tag).ok_or_else(mismatch)?,
+            Value::Short(v) => numeric(v as f64, v as i64, tag).ok_or_else(mismatch)?,
+            Value::Int(v) => numeric(v as f64, v as i64, tag).ok_or_else(mismatch)?,
+            Value::Long(v) => numeric(v as f64, v, tag).ok_or_else(mismatch)?,
+            Value::Float(v) => numeric(v as f64, v as i64, tag).ok_or_else(mismatch)?,
+            Value::Double(v) => numeric(v, v as i64, tag).ok_or_else(mismatch)?,
+            Value::ByteArray(v) => {
+                Value::List(v.into_iter().map(Value::Byte).collect()).cast(tag)?
+            }
+            Value::IntArray(v) => Value::List(v.into_iter().map(Value::Int).collect()).cast(tag)?,
+            Value::LongArray(v) => {
+                Value::List(v.into_iter().map(Value::Long).collect()).cast(tag)?
+            }
+            Value::List(v) => match tag {
+                0x07 => Value::ByteArray(
+                    list(v)?
+                        .into_iter()
+                        .map(|v| if let Value::Byte(v) = v { v } else { 0 })
+                        .collect(),
+                ),
+                0x0b => Value::IntArray(
+                    list(v)?
+                        .into_iter()
+                        .map(|v| if let Value::Int(v) = v { v } else { 0 })
+                        .collect(),
+                ),
+                0x0c => Value::LongArray(
+                    list(v)?
+                        .into_iter()
+                        .map(|v| if let Value::Long(v) = v { v } else { 0 })
+                        .collect(),
+                ),
+                _ => return Err(mismatch()),
+            },
+            _ => return Err(mismatch()),
+        })
+    }
+
+    ///与[`Self::cast`]相同, 但数值超出`tag`类型的范围或转换会损失精度时返回[`Error::Range`]
+    pub fn cast_exact(self, tag: u8) -> IResult<Value> {
+        let from = self.tag();
+        let cast = self.clone().cast(tag)?;
+        match cast.clone().cast(from) {
+            Ok(back) if back == self => Ok(cast),
+            _ => Err(Error::Range {
+                expected: Value::name_of(tag),
+                value: match self {
+                    Value::List(_)
+                    | Value::ByteArray(_)
+                    | Value::IntArray(_)
+                    | Value::LongArray(_) => self.tag_name().to_owned(),
+                    v => v.to_string(),
+                },
+                path: String::new(),
+            }),
+        }
+    }
+}
+
+fn numeric(f: f64, i: i64, tag: u8) -> Option<Value> {
+    Some(match tag {
+        0x01 => Value::Byte(i as i8),
+        0x02 => Value::Short(i as i16),
+        0x03 => Value::Int(i as i32),
+        0x04 => Value::Long(i),
+        0x05 => Value::Float(f as f32),
+        0x06 => Value::Double(f),
+        _ => return None,
+    })
+}
+
+fn element_tag(array: u8) -> u8 {
+    match array {
+        0x07 => 0x01,
+        0x0b => 0x03,
+        0x0c => 0x04,
+        _ => 0,
+    }
+}
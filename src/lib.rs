@@ -5,22 +5,35 @@
 //! 读取NBT并打印
 //!
 //! ```
-//! use crate::*;
+//! use nbtrock::{IResult, NBT};
+//! use std::fs::File;
 //! fn read_example() -> IResult<()> {
 //!     println!(
 //!         "{}",
-//!         NBT::from_path(Path::new("res/gold_farm.mcstructure"))?
+//!         NBT::from_reader(&mut File::open("res/gold_farm.mcstructure")?)?
 //!     );
 //!     Ok(())
 //! }
 //! ```
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use ritelinked::linked_hash_map::LinkedHashMap as Map;
+pub use ritelinked::linked_hash_map::LinkedHashMap as Map;
 use std::{
     fmt::{Debug, Display},
     io::{Cursor, Read, Seek, Write},
 };
 use thiserror::Error;
+mod compound;
+mod convert;
+mod macros;
+pub mod structure;
+pub mod visit;
+
+#[doc(hidden)]
+pub use compound::__private;
+pub use compound::{NbtCompound, NbtValue};
+#[cfg(feature = "derive")]
+pub use nbtrock_derive::NbtCompound;
+
 pub type Cur<'a> = Cursor<&'a mut Vec<u8>>;
 pub type IResult<T> = std::result::Result<T, Error>;
 
@@ -38,9 +51,78 @@ pub enum Error {
     HeterogeneousList,
     #[error("List标签中的类型不唯一")]
     FmtError(#[source] std::fmt::Error),
+    #[error("类型错误: 期望 {expected}, 实际为 {found}, 位于 \"{path}\"")]
+    Type {
+        expected: &'static str,
+        found: &'static str,
+        path: String,
+    },
+    #[error("数值无法无损地以{expected}保存: {value}, 位于 \"{path}\"")]
+    Range {
+        expected: &'static str,
+        value: String,
+        path: String,
+    },
+    #[error("缺少键: \"{0}\"")]
+    MissingKey(String),
+    #[error("长度错误: 期望 {expected}, 实际为 {found}, 位于 \"{path}\"")]
+    Length {
+        expected: usize,
+        found: usize,
+        path: String,
+    },
+    #[error("坐标超出结构范围: {0:?}")]
+    OutOfBounds([i32; 3]),
+    #[error("语法错误: {0}")]
+    Syntax(String),
     #[error("{0}")]
     Unknown(String),
 }
+
+impl Error {
+    ///在错误的路径前加上一级路径
+    pub fn at(self, key: &str) -> Self {
+        match self {
+            Error::Type {
+                expected,
+                found,
+                path,
+            } => Error::Type {
+                expected,
+                found,
+                path: join_path(key, &path),
+            },
+            Error::Range {
+                expected,
+                value,
+                path,
+            } => Error::Range {
+                expected,
+                value,
+                path: join_path(key, &path),
+            },
+            Error::MissingKey(path) => Error::MissingKey(join_path(key, &path)),
+            Error::Length {
+                expected,
+                found,
+                path,
+            } => Error::Length {
+                expected,
+                found,
+                path: join_path(key, &path),
+            },
+            e => e,
+        }
+    }
+}
+
+fn join_path(parent: &str, child: &str) -> String {
+    match (parent.is_empty(), child.is_empty()) {
+        (true, _) => child.to_owned(),
+        (_, true) => parent.to_owned(),
+        _ => format!("{parent}/{child}"),
+    }
+}
 ///表示一个NBT结构及名称
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_rs", derive(serde::Serialize, serde::Deserialize))]
@@ -53,7 +135,7 @@ impl NBT {
     ///从字节流中读取数据返回[`NBT`]
     pub fn new(bytes: &mut Vec<u8>) -> IResult<NBT> {
         let mut c = Cursor::new(bytes);
-        Ok(NBT::read(&mut c)?)
+        NBT::read(&mut c)
     }
 
     pub fn named(name: &str) -> IResult<NBT> {
@@ -67,24 +149,43 @@ impl NBT {
         let mut buf: Vec<u8> = Vec::new();
         r.read_to_end(&mut buf)?;
         let mut c = Cursor::new(&mut buf);
-        Ok(NBT::read(&mut c)?)
+        NBT::read(&mut c)
     }
 
-    ///向字节流中写入NBT数据
-    pub fn write<W: Write>(&self, vec: &mut W, bedrock_header: bool) -> IResult<()> {
-        let mut buf = Vec::<u8>::new();
-        buf.write_u8(0x0a)?;
-        write_string(&mut buf, &self.name)?;
-
-        self.data.write(&mut buf)?;
+    ///读取依次排列的多个没有头部的NBT, 如世界数据库中区块的方块实体记录
+    pub fn read_all(bytes: &[u8]) -> IResult<Vec<NBT>> {
+        let mut buf = bytes.to_vec();
+        let len = buf.len() as u64;
+        let mut c = Cursor::new(&mut buf);
+        let mut list = Vec::new();
+        while c.position() < len {
+            let (tag, name) = read_next_header(&mut c)?;
+            if tag != 0x0a {
+                return Err(Error::Root(tag));
+            }
+            let data = Value::read(tag, &mut c)?;
+            list.push(NBT { name, data });
+        }
+        Ok(list)
+    }
 
+    ///向字节流中写入NBT数据, 写入前会先检查列表元素的类型, 出错时不会写入任何内容
+    pub fn write<W: Write>(&self, vec: &mut W, bedrock_header: bool) -> IResult<()> {
+        self.data.check_lists()?;
         if bedrock_header {
             vec.write_i32::<LE>(0x08)?;
-            vec.write_u32::<LE>(buf.len() as u32)?;
+            vec.write_u32::<LE>(self.encoded_len(false) as u32)?;
         }
 
-        vec.write_all(buf.as_slice())?;
-        Ok(())
+        vec.write_u8(0x0a)?;
+        write_string(vec, &self.name)?;
+        self.data.write(vec)
+    }
+
+    ///返回[`Self::write`]将写入的字节数, 不会实际序列化
+    pub fn encoded_len(&self, bedrock_header: bool) -> usize {
+        let header = if bedrock_header { 8 } else { 0 };
+        header + 1 + 2 + self.name.len() + self.data.encoded_len()
     }
 
     pub fn header<R: Read>(r: &mut R) -> IResult<Option<[u8; 8]>> {
@@ -93,7 +194,7 @@ impl NBT {
             Ok(_) => Some(header),
             Err(_) => None,
         };
-        return Ok(g);
+        Ok(g)
     }
 
     #[inline]
@@ -142,35 +243,20 @@ impl Display for Value {
 
 impl Display for NBT {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut vec = Vec::new();
-        write!(f, "Name: \"{}\"\n", self.name)?;
-        write!(f, "Header: ")?;
-
-        if let Err(_) = self.write(&mut vec, true) {
-            panic!()
+        writeln!(f, "Name: \"{}\"", self.name)?;
+        write!(f, "Header: [")?;
+
+        let len = self.encoded_len(false) as u32;
+        let header = [0x08u32.to_le_bytes(), len.to_le_bytes()].concat();
+        for (index, item) in header.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:#04X}", item)?;
         }
 
-        match NBT::header(&mut vec.as_slice()) {
-            Ok(h) => match h {
-                Some(b) => {
-                    write!(f, "[")?;
-
-                    for (index, item) in b.iter().enumerate() {
-                        if index > 0 {
-                            write!(f, ", ")?;
-                        }
-                        write!(f, "{:#04X}", item)?;
-                    }
-
-                    write!(f, "]")
-                }
-                None => write!(f, "None"),
-            },
-            Err(e) => {
-                panic!("{e}");
-            }
-        }?;
-        write!(f, "\n")?;
+        write!(f, "]")?;
+        writeln!(f)?;
         self.data.print(f, 0)
     }
 }
@@ -251,7 +337,9 @@ impl Value {
         }
     }
 
-    pub fn write(&self, c: &mut Vec<u8>) -> IResult<()> {
+    ///写入标签内容, 遇到元素类型不一致的列表时返回错误, 此时`c`中可能已写入部分数据,
+    ///需要完整检查时使用[`NBT::write`]
+    pub fn write<W: Write>(&self, c: &mut W) -> IResult<()> {
         match *self {
             Value::Byte(v) => c.write_i8(v)?,
             Value::Short(v) => c.write_i16::<LE>(v)?,
@@ -306,20 +394,65 @@ impl Value {
         Ok(())
     }
 
-    pub fn tag_name(&self) -> &str {
-        match *self {
-            Value::Byte(_) => "TAG_Byte",
-            Value::Short(_) => "TAG_Short",
-            Value::Int(_) => "TAG_Int",
-            Value::Long(_) => "TAG_Long",
-            Value::Float(_) => "TAG_Float",
-            Value::Double(_) => "TAG_Double",
-            Value::ByteArray(_) => "TAG_ByteArray",
-            Value::String(_) => "TAG_String",
-            Value::List(_) => "TAG_List",
-            Value::Compound(_) => "TAG_Compound",
-            Value::IntArray(_) => "TAG_IntArray",
-            Value::LongArray(_) => "TAG_LongArray",
+    ///检查所有列表的元素类型是否一致
+    fn check_lists(&self) -> IResult<()> {
+        match self {
+            Value::List(v) => {
+                if let Some(first) = v.first() {
+                    if v.iter().any(|nbt| nbt.tag() != first.tag()) {
+                        return Err(Error::HeterogeneousList);
+                    }
+                }
+                v.iter().try_for_each(Value::check_lists)
+            }
+            Value::Compound(v) => v.values().try_for_each(Value::check_lists),
+            _ => Ok(()),
+        }
+    }
+
+    ///返回标签内容(不含类型ID与名称)序列化后的字节数
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Value::Byte(_) => 1,
+            Value::Short(_) => 2,
+            Value::Int(_) => 4,
+            Value::Long(_) => 8,
+            Value::Float(_) => 4,
+            Value::Double(_) => 8,
+            Value::ByteArray(v) => 4 + v.len(),
+            Value::String(v) => 2 + v.len(),
+            Value::List(v) => 1 + 4 + v.iter().map(Value::encoded_len).sum::<usize>(),
+            Value::Compound(v) => {
+                v.iter()
+                    .map(|(name, nbt)| 1 + 2 + name.len() + nbt.encoded_len())
+                    .sum::<usize>()
+                    + 1
+            }
+            Value::IntArray(v) => 4 + 4 * v.len(),
+            Value::LongArray(v) => 4 + 8 * v.len(),
+        }
+    }
+
+    pub fn tag_name(&self) -> &'static str {
+        Value::name_of(self.tag())
+    }
+
+    ///返回类型ID对应的标签名称
+    pub fn name_of(tag: u8) -> &'static str {
+        match tag {
+            0x01 => "TAG_Byte",
+            0x02 => "TAG_Short",
+            0x03 => "TAG_Int",
+            0x04 => "TAG_Long",
+            0x05 => "TAG_Float",
+            0x06 => "TAG_Double",
+            0x07 => "TAG_ByteArray",
+            0x08 => "TAG_String",
+            0x09 => "TAG_List",
+            0x0a => "TAG_Compound",
+            0x0b => "TAG_IntArray",
+            0x0c => "TAG_LongArray",
+            _ => "TAG_End",
         }
     }
 
@@ -390,11 +523,11 @@ impl Value {
 fn read_next_header(c: &mut Cur) -> IResult<(u8, String)> {
     let tag = c.read_u8()?;
 
-    return if tag == 0x00 {
+    if tag == 0x00 {
         Ok((0x00, "".to_string()))
     } else {
         Ok((tag, read_string(c)?))
-    };
+    }
 }
 
 #[inline]
@@ -417,7 +550,7 @@ fn read_string(c: &mut Cur) -> IResult<String> {
 }
 
 #[inline]
-fn write_string(c: &mut Vec<u8>, s: &str) -> IResult<()> {
+fn write_string<W: Write>(c: &mut W, s: &str) -> IResult<()> {
     let b = s.as_bytes();
     c.write_u16::<LE>(s.len() as u16)?;
     c.write_all(b)?;
@@ -0,0 +1,92 @@
+//! 以类似SNBT的语法构造[`crate::Value`]的宏
+
+///以类似SNBT的语法构造[`crate::Value`]或[`crate::NBT`]
+///
+///数值的类型由字面量后缀决定, 与SNBT保持一致:
+///`i8`为`TAG_Byte`, `i16`为`TAG_Short`, 无后缀整数与`i32`为`TAG_Int`,
+///`i64`为`TAG_Long`, `f32`为`TAG_Float`, 无后缀小数与`f64`为`TAG_Double`,
+///`true`/`false`为`TAG_Byte`
+///
+///List中的元素在编译期被检查为同一类型, `[1i8, 2]`中的`2`会被推断为`i8`,
+///而`[1i8, 2i32]`无法通过编译. `[B; ..]`, `[I; ..]`, `[L; ..]`分别构造
+///`TAG_ByteArray`, `TAG_IntArray`, `TAG_LongArray`
+///
+///值可以是字面量, 变量, `{..}`, `[..]`, 或用括号包裹的任意表达式
+///
+/// # Example
+///
+/// ```
+/// use nbtrock::{nbt, Value};
+///
+/// let count = 1i8;
+/// let chest = nbt!({
+///     "Name": "minecraft:chest",
+///     "Count": count,
+///     "Pos": [0i32, 64, 0],
+///     Motion: [0f64, -0.5, 0.0],
+///     "Tags": [B; 1, 2, 3],
+///     "Slot": (count + 1),
+/// });
+/// assert_eq!(chest["Name"], Value::String("minecraft:chest".into()));
+/// assert_eq!(chest["Slot"], Value::Byte(2));
+///
+/// let root = nbt!("" => { "format_version": 1 });
+/// assert_eq!(root.name, "");
+/// ```
+#[macro_export]
+macro_rules! nbt {
+    (@map $($t:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::Map::<::std::string::String, $crate::Value>::new();
+        $crate::nbt!(@entries map $($t)*);
+        map
+    }};
+
+    (@entries $m:ident) => {};
+    (@entries $m:ident $k:tt : - $v:literal $(, $($rest:tt)*)?) => {
+        $m.insert($crate::nbt!(@key $k), $crate::nbt!(- $v));
+        $crate::nbt!(@entries $m $($($rest)*)?);
+    };
+    (@entries $m:ident $k:tt : $v:tt $(, $($rest:tt)*)?) => {
+        $m.insert($crate::nbt!(@key $k), $crate::nbt!($v));
+        $crate::nbt!(@entries $m $($($rest)*)?);
+    };
+
+    (@key $k:ident) => { ::std::string::String::from(::std::stringify!($k)) };
+    (@key $k:literal) => { ::std::string::String::from($k) };
+
+    (@list [$($out:expr,)*]) => {
+        <[_]>::into_vec(::std::boxed::Box::new([$($out),*]))
+            .into_iter()
+            .map($crate::Value::from)
+            .collect::<::std::vec::Vec<$crate::Value>>()
+    };
+    (@list [$($out:expr,)*] - $v:literal $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@list [$($out,)* -$v,] $($($rest)*)?)
+    };
+    (@list [$($out:expr,)*] { $($t:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@list [$($out,)* $crate::nbt!(@map $($t)*),] $($($rest)*)?)
+    };
+    (@list [$($out:expr,)*] [ $($t:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@list [$($out,)* $crate::nbt!([$($t)*]),] $($($rest)*)?)
+    };
+    (@list [$($out:expr,)*] $v:tt $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@list [$($out,)* $v,] $($($rest)*)?)
+    };
+
+    ($name:literal => { $($t:tt)* }) => {
+        $crate::NBT {
+            name: ::std::string::String::from($name),
+            data: $crate::nbt!({ $($t)* }),
+        }
+    };
+
+    ([B; $($v:expr),* $(,)?]) => { $crate::Value::ByteArray(::std::vec![$($v),*]) };
+    ([I; $($v:expr),* $(,)?]) => { $crate::Value::IntArray(::std::vec![$($v),*]) };
+    ([L; $($v:expr),* $(,)?]) => { $crate::Value::LongArray(::std::vec![$($v),*]) };
+    ([]) => { $crate::Value::List(::std::vec::Vec::new()) };
+    ([ $($t:tt)+ ]) => { $crate::Value::List($crate::nbt!(@list [] $($t)+)) };
+    ({ $($t:tt)* }) => { $crate::Value::Compound($crate::nbt!(@map $($t)*)) };
+    (- $v:literal) => { $crate::Value::from(-$v) };
+    ($v:expr) => { $crate::Value::from($v) };
+}
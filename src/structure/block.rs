@@ -0,0 +1,286 @@
+//! 方块调色板中的方块状态
+use crate::compound::{sort_keys, take};
+use crate::{Error, IResult, Map, NbtCompound, NbtValue, Value};
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+///`block_palette`中的一项, 由方块名称, 方块状态与版本号组成
+///
+///比较时状态与`other`中键的顺序不影响结果, 浮点数按位比较, 因此可以实现[`Eq`]与[`Hash`]
+#[derive(Debug, Clone)]
+pub struct BlockState {
+    pub name: String,
+    pub states: Map<String, Value>,
+    ///旧版本的结构中可能不存在
+    pub version: Option<i32>,
+    ///未被识别的键, 写回时原样保留
+    pub other: Map<String, Value>,
+}
+
+impl BlockState {
+    ///新建方块状态时使用的版本号, 即1.20.0.33
+    pub const VERSION: i32 = 0x01_14_00_21;
+
+    pub fn new(name: &str) -> Self {
+        BlockState {
+            name: name.to_owned(),
+            states: Map::new(),
+            version: Some(BlockState::VERSION),
+            other: Map::new(),
+        }
+    }
+}
+
+impl BlockState {
+    ///名称与状态相同时视为同一种方块, 状态的顺序与版本号不影响比较结果,
+    ///旧版本的调色板项还需要数据值`val`相同
+    pub fn same_block(&self, other: &BlockState) -> bool {
+        self.name == other.name
+            && self.other.get("val") == other.other.get("val")
+            && self.states.len() == other.states.len()
+            && self
+                .states
+                .iter()
+                .all(|(k, v)| other.states.get(k) == Some(v))
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.name == "minecraft:air"
+    }
+}
+
+///解析`name[key=value,...]`, 省略命名空间时补全为`minecraft:`
+///
+///键与字符串值可以使用双引号, 引号中的`\"`与`\\`为转义, 其余字符(包括`,`与`]`)原样保留.
+///`true`/`false`解析为`TAG_Byte`, 整数解析为`TAG_Int`, 其余的值与使用引号的值解析为`TAG_String`
+pub(crate) fn parse_block(s: &str) -> IResult<(String, Map<String, Value>)> {
+    let s = s.trim();
+    let syntax = || Error::Syntax(format!("无效的方块: \"{s}\""));
+    let (name, rest) = match s.find('[') {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(syntax());
+    }
+    let name = match name.contains(':') {
+        true => name.to_owned(),
+        false => format!("minecraft:{name}"),
+    };
+
+    let mut states = Map::new();
+    if let Some(rest) = rest {
+        let mut body = rest.strip_suffix(']').ok_or_else(syntax)?;
+        loop {
+            body = body.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+            if body.is_empty() {
+                break;
+            }
+            let (k, _, rest) = token(body, &['=', ':']).ok_or_else(syntax)?;
+            let rest = rest.strip_prefix(['=', ':']).ok_or_else(syntax)?;
+            let (v, quoted, rest) = token(rest, &[',']).ok_or_else(syntax)?;
+            if !rest.is_empty() && !rest.starts_with(',') {
+                return Err(syntax());
+            }
+            let value = match (quoted, v.as_str()) {
+                (true, _) => Value::String(v),
+                (false, "true") => Value::Byte(1),
+                (false, "false") => Value::Byte(0),
+                (false, _) => match v.parse::<i32>() {
+                    Ok(n) => Value::Int(n),
+                    Err(_) => Value::String(v),
+                },
+            };
+            states.insert(k, value);
+            body = rest;
+        }
+    }
+    Ok((name, states))
+}
+
+///读取一个键或值, 返回内容, 是否使用了引号以及之后的部分.
+///不使用引号时到`end`中的字符为止, 且不能为空或包含`"`, `[`, `]`, `=`
+fn token<'a>(s: &'a str, end: &[char]) -> Option<(String, bool, &'a str)> {
+    let s = s.trim_start();
+    let Some(quoted) = s.strip_prefix('"') else {
+        let i = s.find(end).unwrap_or(s.len());
+        let v = s[..i].trim();
+        let valid = !v.is_empty() && !v.contains(['"', '[', ']', '=']);
+        return valid.then(|| (v.to_owned(), false, &s[i..]));
+    };
+    let mut out = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, true, quoted[i + 1..].trim_start())),
+            '\\' => out.push(chars.next()?.1),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+///按[`parse_block`]能够解析回相同内容的格式写出键或字符串值
+fn quoted(s: &str) -> String {
+    let plain = !s.is_empty()
+        && !matches!(s, "true" | "false")
+        && s.parse::<i32>().is_err()
+        && !s.contains(|c: char| "\",:=[]\\".contains(c) || c.is_whitespace());
+    if plain {
+        return s.to_owned();
+    }
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///解析`name[key=value,...]`, 状态按字节序排列并按照类型表转换值的类型,
+///版本号为[`BlockState::VERSION`]
+///
+/// ```
+/// use nbtrock::{structure::BlockState, Value};
+///
+/// let block: BlockState = "trapdoor[open_bit=1,direction=2]".parse().unwrap();
+/// assert_eq!(block.to_string(), "minecraft:trapdoor[direction=2,open_bit=1]");
+/// assert_eq!(block.states["open_bit"], Value::Byte(1));
+/// ```
+impl FromStr for BlockState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> IResult<Self> {
+        let (name, states) = parse_block(s)?;
+        let mut block = BlockState::new(&name);
+        block.states = states;
+        block.sort_states();
+        block.coerce_states();
+        Ok(block)
+    }
+}
+
+impl PartialEq for BlockState {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.version == other.version
+            && map_eq(&self.states, &other.states)
+            && map_eq(&self.other, &other.other)
+    }
+}
+
+impl Eq for BlockState {}
+
+///与[`PartialEq`]一致, 按键排序后计算, 与键的顺序无关
+impl Hash for BlockState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.version.hash(state);
+        hash_map(&self.states, state);
+        hash_map(&self.other, state);
+    }
+}
+
+///与键的顺序无关的比较
+fn map_eq(a: &Map<String, Value>, b: &Map<String, Value>) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(k, v)| b.get(k).is_some_and(|w| value_eq(v, w)))
+}
+
+///浮点数按位比较, 使`NaN`等于自身
+fn value_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+        (Value::List(a), Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| value_eq(a, b))
+        }
+        (Value::Compound(a), Value::Compound(b)) => map_eq(a, b),
+        (a, b) => a == b,
+    }
+}
+
+fn hash_map<H: Hasher>(map: &Map<String, Value>, state: &mut H) {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(k, _)| *k);
+    entries.len().hash(state);
+    for (k, v) in entries {
+        k.hash(state);
+        hash_value(v, state);
+    }
+}
+
+fn hash_value<H: Hasher>(v: &Value, state: &mut H) {
+    v.tag().hash(state);
+    match v {
+        Value::String(s) => s.hash(state),
+        Value::Float(f) => f.to_bits().hash(state),
+        Value::Double(f) => f.to_bits().hash(state),
+        Value::List(list) => {
+            list.len().hash(state);
+            list.iter().for_each(|v| hash_value(v, state));
+        }
+        Value::Compound(map) => hash_map(map, state),
+        Value::ByteArray(a) => a.hash(state),
+        Value::IntArray(a) => a.hash(state),
+        Value::LongArray(a) => a.hash(state),
+        v => v.to_i64().hash(state),
+    }
+}
+
+///格式为`name[key=value,...]`, 没有状态时只有名称, 特殊的键与字符串值会加上双引号
+impl Display for BlockState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.states.is_empty() {
+            return Ok(());
+        }
+        write!(f, "[")?;
+        for (index, (k, v)) in self.states.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            match v {
+                Value::String(v) => write!(f, "{}={}", quoted(k), quoted(v))?,
+                v => write!(f, "{}={v}", quoted(k))?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+impl NbtCompound for BlockState {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(BlockState {
+            name: take(map, "name")?,
+            states: crate::__private::read(map, "states")?.unwrap_or_default(),
+            version: crate::__private::read(map, "version")?,
+            other: std::mem::take(map),
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        map.insert("name".to_owned(), Value::String(self.name.clone()));
+        //旧版本的调色板使用`val`而不是`states`
+        if self.version.is_some() || !self.states.is_empty() {
+            map.insert("states".to_owned(), Value::Compound(self.states.clone()));
+        }
+        if let Some(version) = self.version {
+            map.insert("version".to_owned(), Value::Int(version));
+        }
+        if !self.other.is_empty() {
+            map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+            sort_keys(map);
+        }
+    }
+}
+
+impl NbtValue for BlockState {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
@@ -0,0 +1,500 @@
+//! `block_position_data`中`block_entity_data`的类型化模型
+//!
+//! 未被识别的键保存在各自的`other`中, 写回时所有键按字节序排列, 与游戏保存的顺序一致,
+//! 因此游戏生成的数据可以原样写回. 缺少的键读取为默认值, 写回时只要值未被修改就不会写入
+use super::{ItemStack, McStructure};
+use crate::compound::{drop_filled, filled_keys, put, sort_keys, take};
+use crate::{__private::read, IResult, Map, NbtCompound, NbtValue, Value};
+
+fn read_or<T: NbtValue + Default>(map: &mut Map<String, Value>, key: &str) -> IResult<T> {
+    Ok(read(map, key)?.unwrap_or_default())
+}
+
+///方块实体, 包含所有方块实体共有的键
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+    pub id: String,
+    ///世界坐标`x`, `y`, `z`
+    pub pos: [i32; 3],
+    pub is_movable: bool,
+    pub custom_name: Option<String>,
+    pub kind: BlockEntityKind,
+    ///未被识别的键
+    pub other: Map<String, Value>,
+    ///读取时缺少而以默认值补全的键, 值未被修改时不会写回
+    pub filled: Map<String, Value>,
+}
+
+///按`id`区分的方块实体数据
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockEntityKind {
+    ///`Chest`
+    Chest(Chest),
+    ///`Barrel`
+    Barrel(Barrel),
+    ///`Hopper`
+    Hopper(Hopper),
+    ///`Furnace`, `BlastFurnace`与`Smoker`
+    Furnace(Furnace),
+    ///`Sign`与`HangingSign`
+    Sign(Box<Sign>),
+    ///`CommandBlock`
+    CommandBlock(CommandBlock),
+    ///`Beacon`
+    Beacon(Beacon),
+    ///其他方块实体, 所有数据都在[`BlockEntity::other`]中
+    Other,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chest {
+    pub items: Vec<ItemStack>,
+    pub findable: bool,
+    ///大箱子的另一半
+    pub pair: Option<ChestPair>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChestPair {
+    ///是否是大箱子中保存物品的一半
+    pub lead: bool,
+    pub x: i32,
+    pub z: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Barrel {
+    pub items: Vec<ItemStack>,
+    pub findable: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hopper {
+    pub items: Vec<ItemStack>,
+    pub transfer_cooldown: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Furnace {
+    pub items: Vec<ItemStack>,
+    pub burn_time: i16,
+    pub cook_time: i16,
+    pub burn_duration: i16,
+    ///`StoredXPInt`
+    pub stored_xp: i32,
+}
+
+///告示牌, 1.19.80之前的告示牌只有一面, 文字直接保存在方块实体中, 此时`legacy`为`true`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sign {
+    ///`FrontText`
+    pub front: SignText,
+    ///`BackText`
+    pub back: Option<SignText>,
+    pub is_waxed: bool,
+    ///以旧版本的单面格式写回, 此时忽略`back`与`is_waxed`
+    pub legacy: bool,
+}
+
+///告示牌一面的文字
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignText {
+    pub text: String,
+    pub text_owner: String,
+    ///ARGB颜色
+    pub sign_text_color: i32,
+    pub persist_formatting: bool,
+    pub hide_glow_outline: bool,
+    pub ignore_lighting: bool,
+    ///未被识别的键
+    pub other: Map<String, Value>,
+    ///读取时缺少而以默认值补全的键, 值未被修改时不会写回
+    pub filled: Map<String, Value>,
+}
+
+impl Default for SignText {
+    fn default() -> Self {
+        SignText {
+            text: String::new(),
+            text_owner: String::new(),
+            sign_text_color: 0xff000000_u32 as i32,
+            persist_formatting: true,
+            hide_glow_outline: false,
+            ignore_lighting: false,
+            other: Map::new(),
+            filled: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandBlock {
+    pub command: String,
+    pub last_output: String,
+    pub track_output: bool,
+    pub success_count: i32,
+    ///`auto`, 为`true`时无需红石信号
+    pub auto: bool,
+    pub powered: bool,
+    pub condition_met: bool,
+    pub conditional_mode: bool,
+    pub tick_delay: i32,
+    pub execute_on_first_tick: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Beacon {
+    ///主效果的状态效果ID, 0表示没有
+    pub primary: i32,
+    pub secondary: i32,
+}
+
+impl NbtCompound for Chest {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        let pair = match map.contains_key("pairx") {
+            true => Some(ChestPair {
+                lead: read_or(map, "pairlead")?,
+                x: take(map, "pairx")?,
+                z: take(map, "pairz")?,
+            }),
+            false => None,
+        };
+        Ok(Chest {
+            items: read_or(map, "Items")?,
+            findable: read_or(map, "Findable")?,
+            pair,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Findable", &self.findable);
+        put(map, "Items", &self.items);
+        if let Some(pair) = self.pair {
+            put(map, "pairlead", &pair.lead);
+            put(map, "pairx", &pair.x);
+            put(map, "pairz", &pair.z);
+        }
+    }
+}
+
+impl NbtCompound for Barrel {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(Barrel {
+            items: read_or(map, "Items")?,
+            findable: read_or(map, "Findable")?,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Findable", &self.findable);
+        put(map, "Items", &self.items);
+    }
+}
+
+impl NbtCompound for Hopper {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(Hopper {
+            items: read_or(map, "Items")?,
+            transfer_cooldown: read_or(map, "TransferCooldown")?,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Items", &self.items);
+        put(map, "TransferCooldown", &self.transfer_cooldown);
+    }
+}
+
+impl NbtCompound for Furnace {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(Furnace {
+            items: read_or(map, "Items")?,
+            burn_time: read_or(map, "BurnTime")?,
+            cook_time: read_or(map, "CookTime")?,
+            burn_duration: read_or(map, "BurnDuration")?,
+            stored_xp: read_or(map, "StoredXPInt")?,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "BurnDuration", &self.burn_duration);
+        put(map, "BurnTime", &self.burn_time);
+        put(map, "CookTime", &self.cook_time);
+        put(map, "Items", &self.items);
+        put(map, "StoredXPInt", &self.stored_xp);
+    }
+}
+
+impl SignText {
+    ///只读取已知的键, 旧版本告示牌的文字与方块实体的其他键混在一起
+    fn read_fields(map: &mut Map<String, Value>) -> IResult<Self> {
+        let default = SignText::default();
+        Ok(SignText {
+            text: read_or(map, "Text")?,
+            text_owner: read_or(map, "TextOwner")?,
+            sign_text_color: read(map, "SignTextColor")?.unwrap_or(default.sign_text_color),
+            persist_formatting: read(map, "PersistFormatting")?
+                .unwrap_or(default.persist_formatting),
+            hide_glow_outline: read_or(map, "HideGlowOutline")?,
+            ignore_lighting: read_or(map, "IgnoreLighting")?,
+            other: Map::new(),
+            filled: Map::new(),
+        })
+    }
+}
+
+impl NbtCompound for SignText {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        let keys = map.keys().cloned().collect::<Vec<_>>();
+        let mut text = SignText::read_fields(map)?;
+        text.other = std::mem::take(map);
+        text.filled = filled_keys(&keys, text.to_compound());
+        Ok(text)
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "HideGlowOutline", &self.hide_glow_outline);
+        put(map, "IgnoreLighting", &self.ignore_lighting);
+        put(map, "PersistFormatting", &self.persist_formatting);
+        put(map, "SignTextColor", &self.sign_text_color);
+        put(map, "Text", &self.text);
+        put(map, "TextOwner", &self.text_owner);
+        map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        sort_keys(map);
+        drop_filled(map, &self.filled);
+    }
+}
+
+impl NbtValue for SignText {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
+
+impl NbtCompound for Sign {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        if !map.contains_key("FrontText") {
+            return Ok(Sign {
+                front: SignText::read_fields(map)?,
+                back: None,
+                is_waxed: false,
+                legacy: true,
+            });
+        }
+        Ok(Sign {
+            front: take(map, "FrontText")?,
+            back: read(map, "BackText")?,
+            is_waxed: read_or(map, "IsWaxed")?,
+            legacy: false,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        if self.legacy {
+            return self.front.write_compound(map);
+        }
+        if let Some(back) = &self.back {
+            put(map, "BackText", back);
+        }
+        put(map, "FrontText", &self.front);
+        put(map, "IsWaxed", &self.is_waxed);
+    }
+}
+
+impl NbtCompound for CommandBlock {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(CommandBlock {
+            command: read_or(map, "Command")?,
+            last_output: read_or(map, "LastOutput")?,
+            track_output: read_or(map, "TrackOutput")?,
+            success_count: read_or(map, "SuccessCount")?,
+            auto: read_or(map, "auto")?,
+            powered: read_or(map, "powered")?,
+            condition_met: read_or(map, "conditionMet")?,
+            conditional_mode: read_or(map, "conditionalMode")?,
+            tick_delay: read_or(map, "TickDelay")?,
+            execute_on_first_tick: read_or(map, "ExecuteOnFirstTick")?,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Command", &self.command);
+        put(map, "ExecuteOnFirstTick", &self.execute_on_first_tick);
+        put(map, "LastOutput", &self.last_output);
+        put(map, "SuccessCount", &self.success_count);
+        put(map, "TickDelay", &self.tick_delay);
+        put(map, "TrackOutput", &self.track_output);
+        put(map, "auto", &self.auto);
+        put(map, "conditionMet", &self.condition_met);
+        put(map, "conditionalMode", &self.conditional_mode);
+        put(map, "powered", &self.powered);
+    }
+}
+
+impl NbtCompound for Beacon {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(Beacon {
+            primary: read_or(map, "primary")?,
+            secondary: read_or(map, "secondary")?,
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "primary", &self.primary);
+        put(map, "secondary", &self.secondary);
+    }
+}
+
+impl BlockEntity {
+    ///新建指定`id`的方块实体, 已知类型的数据为默认值
+    pub fn new(id: &str) -> Self {
+        let kind = match id {
+            "Chest" => BlockEntityKind::Chest(Chest::default()),
+            "Barrel" => BlockEntityKind::Barrel(Barrel::default()),
+            "Hopper" => BlockEntityKind::Hopper(Hopper::default()),
+            "Furnace" | "BlastFurnace" | "Smoker" => BlockEntityKind::Furnace(Furnace::default()),
+            "Sign" | "HangingSign" => BlockEntityKind::Sign(Box::new(Sign {
+                back: Some(SignText::default()),
+                ..Default::default()
+            })),
+            "CommandBlock" => BlockEntityKind::CommandBlock(CommandBlock::default()),
+            "Beacon" => BlockEntityKind::Beacon(Beacon::default()),
+            _ => BlockEntityKind::Other,
+        };
+        BlockEntity {
+            id: id.to_owned(),
+            pos: [0; 3],
+            is_movable: true,
+            custom_name: None,
+            kind,
+            other: Map::new(),
+            filled: Map::new(),
+        }
+    }
+
+    ///容器中的物品, 不是已知的容器时返回`None`
+    pub fn items(&self) -> Option<&Vec<ItemStack>> {
+        match &self.kind {
+            BlockEntityKind::Chest(c) => Some(&c.items),
+            BlockEntityKind::Barrel(c) => Some(&c.items),
+            BlockEntityKind::Hopper(c) => Some(&c.items),
+            BlockEntityKind::Furnace(c) => Some(&c.items),
+            _ => None,
+        }
+    }
+
+    pub fn items_mut(&mut self) -> Option<&mut Vec<ItemStack>> {
+        match &mut self.kind {
+            BlockEntityKind::Chest(c) => Some(&mut c.items),
+            BlockEntityKind::Barrel(c) => Some(&mut c.items),
+            BlockEntityKind::Hopper(c) => Some(&mut c.items),
+            BlockEntityKind::Furnace(c) => Some(&mut c.items),
+            _ => None,
+        }
+    }
+}
+
+impl NbtCompound for BlockEntity {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        let keys = map.keys().cloned().collect::<Vec<_>>();
+        let id: String = take(map, "id")?;
+        let pos = [read_or(map, "x")?, read_or(map, "y")?, read_or(map, "z")?];
+        let kind = match id.as_str() {
+            "Chest" => BlockEntityKind::Chest(Chest::read_compound(map)?),
+            "Barrel" => BlockEntityKind::Barrel(Barrel::read_compound(map)?),
+            "Hopper" => BlockEntityKind::Hopper(Hopper::read_compound(map)?),
+            "Furnace" | "BlastFurnace" | "Smoker" => {
+                BlockEntityKind::Furnace(Furnace::read_compound(map)?)
+            }
+            "Sign" | "HangingSign" => BlockEntityKind::Sign(Box::new(Sign::read_compound(map)?)),
+            "CommandBlock" => BlockEntityKind::CommandBlock(CommandBlock::read_compound(map)?),
+            "Beacon" => BlockEntityKind::Beacon(Beacon::read_compound(map)?),
+            _ => BlockEntityKind::Other,
+        };
+        let mut entity = BlockEntity {
+            id,
+            pos,
+            is_movable: read(map, "isMovable")?.unwrap_or(true),
+            custom_name: read(map, "CustomName")?,
+            kind,
+            other: std::mem::take(map),
+            filled: Map::new(),
+        };
+        entity.filled = filled_keys(&keys, entity.to_compound());
+        Ok(entity)
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "id", &self.id);
+        put(map, "isMovable", &self.is_movable);
+        if let Some(name) = &self.custom_name {
+            put(map, "CustomName", name);
+        }
+        for (key, v) in ["x", "y", "z"].into_iter().zip(self.pos) {
+            put(map, key, &v);
+        }
+        match &self.kind {
+            BlockEntityKind::Chest(v) => v.write_compound(map),
+            BlockEntityKind::Barrel(v) => v.write_compound(map),
+            BlockEntityKind::Hopper(v) => v.write_compound(map),
+            BlockEntityKind::Furnace(v) => v.write_compound(map),
+            BlockEntityKind::Sign(v) => v.write_compound(map),
+            BlockEntityKind::CommandBlock(v) => v.write_compound(map),
+            BlockEntityKind::Beacon(v) => v.write_compound(map),
+            BlockEntityKind::Other => {}
+        }
+        map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        sort_keys(map);
+        drop_filled(map, &self.filled);
+    }
+}
+
+impl NbtValue for BlockEntity {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
+
+impl McStructure {
+    ///该位置的方块实体, 坐标越界或没有方块实体时返回`None`
+    pub fn block_entity(&self, x: i32, y: i32, z: i32) -> IResult<Option<BlockEntity>> {
+        let Some(index) = self.index(x, y, z) else {
+            return Ok(None);
+        };
+        self.block_position_data
+            .get(&(index as i32))
+            .and_then(|data| data.get("block_entity_data"))
+            .map(|v| BlockEntity::from_value(v.clone()))
+            .transpose()
+    }
+
+    ///设置该位置的方块实体, `pos`会被设置为对应的世界坐标
+    pub fn set_block_entity(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        mut entity: BlockEntity,
+    ) -> IResult<()> {
+        let index = self.checked_index(x, y, z)?;
+        entity.pos = [0, 1, 2].map(|i| self.structure_world_origin[i] + [x, y, z][i]);
+        let data = self
+            .block_position_data
+            .entry(index as i32)
+            .or_insert_with(Map::new);
+        data.insert("block_entity_data".to_owned(), entity.to_value());
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! 命令方块的列举与批量修改
+//!
+//! 除结构外, 也可以修改世界数据库中区块的方块实体记录(键的最后一个字节为`0x31`).
+//! 本库不读写LevelDB, 记录需要用其他工具取出, 以[`NBT::read_all`]读取, 修改后依次写回
+use super::{BlockEntity, BlockEntityKind, McStructure};
+use crate::{compound::sort_keys, NbtValue, Value, NBT};
+
+///命令方块的类型, 由方块名称决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandBlockMode {
+    ///`minecraft:command_block`
+    Impulse,
+    ///`minecraft:repeating_command_block`
+    Repeating,
+    ///`minecraft:chain_command_block`
+    Chain,
+}
+
+impl CommandBlockMode {
+    pub fn from_block_name(name: &str) -> Option<Self> {
+        match name {
+            "minecraft:command_block" => Some(CommandBlockMode::Impulse),
+            "minecraft:repeating_command_block" => Some(CommandBlockMode::Repeating),
+            "minecraft:chain_command_block" => Some(CommandBlockMode::Chain),
+            _ => None,
+        }
+    }
+}
+
+///结构中的一个命令方块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandBlockInfo {
+    ///相对坐标
+    pub pos: [i32; 3],
+    ///方块不是命令方块时为`None`
+    pub mode: Option<CommandBlockMode>,
+    ///方块状态`conditional_bit`
+    pub conditional: bool,
+    pub command: String,
+    ///`auto`, 为`true`时无需红石信号
+    pub auto: bool,
+    pub tick_delay: i32,
+    pub custom_name: Option<String>,
+}
+
+impl McStructure {
+    ///按下标顺序列出所有`id`为`CommandBlock`的方块实体, 无法读取的方块实体会被跳过
+    pub fn command_blocks(&self) -> Vec<CommandBlockInfo> {
+        let mut keys = self.command_block_keys();
+        keys.sort_unstable();
+        keys.into_iter()
+            .filter_map(|key| self.command_block_info(key))
+            .collect()
+    }
+
+    ///用`f`的返回值替换命令方块的命令, 返回`None`或与原命令相同时不做修改,
+    ///返回被修改的命令方块数. 只修改`block_entity_data`中的`Command`, 其余的键原样保留,
+    ///无法读取的方块实体会被跳过
+    ///
+    /// ```
+    /// use nbtrock::structure::{BlockEntity, BlockEntityKind, BlockState, McStructure};
+    ///
+    /// let mut s = McStructure::new([1, 1, 1]);
+    /// s.set_block(0, 0, 0, BlockState::new("minecraft:command_block")).unwrap();
+    /// let mut block = BlockEntity::new("CommandBlock");
+    /// if let BlockEntityKind::CommandBlock(c) = &mut block.kind {
+    ///     c.command = "tp @a 0 64 0".to_owned();
+    /// }
+    /// s.set_block_entity(0, 0, 0, block).unwrap();
+    ///
+    /// let n = s.rewrite_commands(|b| Some(b.command.replace("@a", "@a[tag=player]")));
+    /// assert_eq!(n, 1);
+    /// assert_eq!(s.command_blocks()[0].command, "tp @a[tag=player] 0 64 0");
+    /// ```
+    pub fn rewrite_commands<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&CommandBlockInfo) -> Option<String>,
+    {
+        let mut keys = self.command_block_keys();
+        keys.sort_unstable();
+        let mut changed = 0;
+        for key in keys {
+            let Some(info) = self.command_block_info(key) else {
+                continue;
+            };
+            let data = self
+                .block_position_data
+                .get_mut(&key)
+                .and_then(|data| data.get_mut("block_entity_data"));
+            if let Some(data) = data {
+                changed += rewrite(data, &info, &mut f) as usize;
+            }
+        }
+        changed
+    }
+
+    ///超出结构范围的下标会被跳过
+    fn command_block_keys(&self) -> Vec<i32> {
+        self.block_position_data
+            .iter()
+            .filter(|(&key, _)| (0..self.volume() as i32).contains(&key))
+            .filter(|(_, data)| data.get("block_entity_data").is_some_and(is_command_block))
+            .map(|(&key, _)| key)
+            .collect()
+    }
+
+    fn command_block_info(&self, key: i32) -> Option<CommandBlockInfo> {
+        let (block, custom_name) = read(&self.block_position_data[&key]["block_entity_data"])?;
+        let palette = self.block_indices[0]
+            .get(key as usize)
+            .copied()
+            .unwrap_or(-1);
+        let state = self.palette_entry(palette);
+        Some(CommandBlockInfo {
+            pos: self.position(key as usize),
+            mode: state.and_then(|b| CommandBlockMode::from_block_name(&b.name)),
+            conditional: state
+                .and_then(|b| b.states.get("conditional_bit"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            command: block.command,
+            auto: block.auto,
+            tick_delay: block.tick_delay,
+            custom_name,
+        })
+    }
+}
+
+///按顺序列出世界数据库方块实体记录中的命令方块, `pos`为世界坐标.
+///记录中没有方块信息, 因此`mode`为`None`, `conditional`取自`conditionalMode`,
+///无法读取的方块实体会被跳过
+pub fn world_command_blocks(records: &[NBT]) -> Vec<CommandBlockInfo> {
+    records
+        .iter()
+        .filter_map(|record| world_info(&record.data))
+        .collect()
+}
+
+///与[`McStructure::rewrite_commands`]相同, 修改世界数据库方块实体记录中的命令方块
+///
+/// ```
+/// use nbtrock::structure::{rewrite_world_commands, world_command_blocks};
+/// use nbtrock::{nbt, NBT};
+///
+/// let block = nbt!({ Command: "say 1", id: "CommandBlock", x: 10, y: 64, z: -3 });
+/// let mut bytes = Vec::new();
+/// for _ in 0..2 {
+///     NBT { name: String::new(), data: block.clone() }.write(&mut bytes, false).unwrap();
+/// }
+/// let mut records = NBT::read_all(&bytes).unwrap();
+/// assert_eq!(rewrite_world_commands(&mut records, |b| Some(b.command.replace('1', "2"))), 2);
+/// assert_eq!(world_command_blocks(&records)[1].command, "say 2");
+/// ```
+pub fn rewrite_world_commands<F>(records: &mut [NBT], mut f: F) -> usize
+where
+    F: FnMut(&CommandBlockInfo) -> Option<String>,
+{
+    let mut changed = 0;
+    for record in records {
+        if let Some(info) = world_info(&record.data) {
+            changed += rewrite(&mut record.data, &info, &mut f) as usize;
+        }
+    }
+    changed
+}
+
+fn is_command_block(data: &Value) -> bool {
+    data.get("id").and_then(Value::as_str) == Some("CommandBlock")
+}
+
+///读取命令方块的数据与自定义名称, 不是命令方块或无法读取时返回`None`
+fn read(data: &Value) -> Option<(super::CommandBlock, Option<String>)> {
+    if !is_command_block(data) {
+        return None;
+    }
+    let entity = BlockEntity::from_value(data.clone()).ok()?;
+    match entity.kind {
+        BlockEntityKind::CommandBlock(block) => Some((block, entity.custom_name)),
+        _ => None,
+    }
+}
+
+fn world_info(data: &Value) -> Option<CommandBlockInfo> {
+    let (block, custom_name) = read(data)?;
+    let pos = ["x", "y", "z"].map(|k| data.get(k).and_then(Value::to_i32));
+    let [Some(x), Some(y), Some(z)] = pos else {
+        return None;
+    };
+    Some(CommandBlockInfo {
+        pos: [x, y, z],
+        mode: None,
+        conditional: block.conditional_mode,
+        command: block.command,
+        auto: block.auto,
+        tick_delay: block.tick_delay,
+        custom_name,
+    })
+}
+
+///以`f`的返回值替换`data`中的`Command`, 返回是否修改
+fn rewrite<F>(data: &mut Value, info: &CommandBlockInfo, f: &mut F) -> bool
+where
+    F: FnMut(&CommandBlockInfo) -> Option<String>,
+{
+    let Some(command) = f(info).filter(|c| *c != info.command) else {
+        return false;
+    };
+    let Some(data) = data.as_compound_mut() else {
+        return false;
+    };
+    //保持`Command`在`block_entity_data`中的位置
+    match data.get_mut("Command") {
+        Some(v) => *v = Value::String(command),
+        None => {
+            data.insert("Command".to_owned(), Value::String(command));
+            sort_keys(data);
+        }
+    }
+    true
+}
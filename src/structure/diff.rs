@@ -0,0 +1,195 @@
+//! 两个结构之间按方块比较的差异
+//!
+//! 两个结构按相对坐标对齐, 比较范围是两者大小的并集, 超出某个结构的位置视为structure_void.
+//! 方块按名称与状态比较, 与调色板的顺序无关
+use super::{transform::set_int, BlockState, McStructure};
+use crate::{Map, Value};
+
+///某个位置上的方块变化, `None`表示structure_void
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDiff {
+    pub pos: [i32; 3],
+    ///0为主层, 1为次层
+    pub layer: usize,
+    pub before: Option<BlockState>,
+    pub after: Option<BlockState>,
+}
+
+///某个位置上`block_position_data`的变化
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDiff {
+    pub pos: [i32; 3],
+    pub before: Option<Map<String, Value>>,
+    pub after: Option<Map<String, Value>>,
+}
+
+///[`McStructure::diff`]的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructureDiff {
+    ///比较范围的大小
+    pub size: [i32; 3],
+    pub blocks: Vec<BlockDiff>,
+    pub block_data: Vec<DataDiff>,
+    ///只存在于新结构中的实体
+    pub entities_added: Vec<Map<String, Value>>,
+    ///只存在于旧结构中的实体
+    pub entities_removed: Vec<Map<String, Value>>,
+}
+
+///去除`block_entity_data`中的世界坐标`x`, `y`, `z`, 并将大箱子的`pairx`, `pairz`
+///换算为相对结构原点的坐标, 使不同位置的结构可以比较
+fn normalize_data(data: &Map<String, Value>, origin: [i32; 3]) -> Map<String, Value> {
+    let mut data = data.clone();
+    if let Some(Value::Compound(entity)) = data.get_mut("block_entity_data") {
+        for key in ["x", "y", "z"] {
+            entity.remove(key);
+        }
+        for (key, origin) in [("pairx", origin[0]), ("pairz", origin[2])] {
+            if let Some(v) = entity.get_mut(key) {
+                if let Some(n) = v.to_i64() {
+                    set_int(v, n - origin as i64);
+                }
+            }
+        }
+    }
+    data
+}
+
+///去除每次保存都会变化的`UniqueID`, 并将`Pos`换算为相对结构原点的坐标
+fn normalize_entity(entity: &Map<String, Value>, origin: [i32; 3]) -> Map<String, Value> {
+    let mut entity = entity.clone();
+    entity.remove("UniqueID");
+    if let Some(Value::List(pos)) = entity.get_mut("Pos") {
+        for (p, origin) in pos.iter_mut().zip(origin) {
+            if let Some(v) = p.to_f64() {
+                *p = Value::Double(v - origin as f64);
+            }
+        }
+    }
+    entity
+}
+
+impl McStructure {
+    fn block_at(&self, layer: usize, pos: [i32; 3]) -> Option<&BlockState> {
+        let [x, y, z] = pos;
+        self.palette_entry(self.block_indices[layer][self.index(x, y, z)?])
+    }
+
+    fn data_at(&self, pos: [i32; 3]) -> Option<&Map<String, Value>> {
+        let [x, y, z] = pos;
+        self.block_position_data.get(&(self.index(x, y, z)? as i32))
+    }
+
+    ///与新版本`other`比较
+    pub fn diff(&self, other: &McStructure) -> StructureDiff {
+        let size = [0, 1, 2].map(|i| self.size[i].max(other.size[i]).max(0));
+        let mut diff = StructureDiff {
+            size,
+            ..Default::default()
+        };
+        for x in 0..size[0] {
+            for y in 0..size[1] {
+                for z in 0..size[2] {
+                    let pos = [x, y, z];
+                    for layer in 0..2 {
+                        let (before, after) =
+                            (self.block_at(layer, pos), other.block_at(layer, pos));
+                        let same = match (before, after) {
+                            (Some(a), Some(b)) => a.same_block(b),
+                            (a, b) => a.is_none() && b.is_none(),
+                        };
+                        if !same {
+                            diff.blocks.push(BlockDiff {
+                                pos,
+                                layer,
+                                before: before.cloned(),
+                                after: after.cloned(),
+                            });
+                        }
+                    }
+
+                    let (before, after) = (self.data_at(pos), other.data_at(pos));
+                    let normalize =
+                        |data, s: &McStructure| normalize_data(data, s.structure_world_origin);
+                    if before.map(|d| normalize(d, self)) != after.map(|d| normalize(d, other)) {
+                        diff.block_data.push(DataDiff {
+                            pos,
+                            before: before.cloned(),
+                            after: after.cloned(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let old = self
+            .entities
+            .iter()
+            .map(|e| normalize_entity(e, self.structure_world_origin))
+            .collect::<Vec<_>>();
+        let mut matched = vec![false; old.len()];
+        for entity in &other.entities {
+            let normalized = normalize_entity(entity, other.structure_world_origin);
+            match (0..old.len()).find(|&i| !matched[i] && old[i] == normalized) {
+                Some(i) => matched[i] = true,
+                None => diff.entities_added.push(entity.clone()),
+            }
+        }
+        diff.entities_removed = self
+            .entities
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(e, _)| e.clone())
+            .collect();
+        diff
+    }
+}
+
+impl StructureDiff {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+            && self.block_data.is_empty()
+            && self.entities_added.is_empty()
+            && self.entities_removed.is_empty()
+    }
+
+    ///只包含差异的结构, 其余位置为structure_void, `new`为比较时的新结构
+    ///
+    ///新增与改变的方块使用新结构中的方块, 被移除的方块变为空气.
+    ///`block_position_data`发生变化的位置会连同方块一起从新结构复制, 并包含新增的实体
+    pub fn to_structure(&self, new: &McStructure) -> McStructure {
+        let mut structure = McStructure::new(self.size);
+        structure.structure_world_origin = new.structure_world_origin;
+        let air = BlockState::new("minecraft:air");
+        for diff in &self.blocks {
+            let [x, y, z] = diff.pos;
+            let index = structure.index(x, y, z).unwrap();
+            let block = match &diff.after {
+                Some(b) => b,
+                None if diff.layer == 0 => &air,
+                None => continue,
+            };
+            let palette = structure.palette_index(block);
+            structure.block_indices[diff.layer][index] = palette;
+        }
+        for diff in &self.block_data {
+            let [x, y, z] = diff.pos;
+            let Some(after) = &diff.after else {
+                continue;
+            };
+            let index = structure.index(x, y, z).unwrap();
+            for layer in 0..2 {
+                if let Some(block) = new.block_at(layer, diff.pos) {
+                    let palette = structure.palette_index(block);
+                    structure.block_indices[layer][index] = palette;
+                }
+            }
+            structure
+                .block_position_data
+                .insert(index as i32, after.clone());
+        }
+        structure.entities = self.entities_added.clone();
+        structure
+    }
+}
@@ -0,0 +1,245 @@
+//! `structure.entities`中实体的类型化模型
+//!
+//! 与方块实体相同, 写回时键按字节序排列. 世界数据库中的`actorprefix`记录格式相同,
+//! 也可以用[`Entity`]读写
+use super::{ItemStack, McStructure};
+use crate::compound::{drop_filled, filled_keys, put, sort_keys, take};
+use crate::visit::{walk_compound_mut, VisitMut};
+use crate::{__private::read, IResult, Map, NbtCompound, NbtValue, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hasher};
+
+fn read_or<T: NbtValue + Default>(map: &mut Map<String, Value>, key: &str) -> IResult<T> {
+    Ok(read(map, key)?.unwrap_or_default())
+}
+
+///保存其他实体`UniqueID`的键, 重新生成`UniqueID`时会一并修改
+const REFERENCE_KEYS: [&str; 4] = ["LeasherID", "OwnerNew", "TargetID", "entityID"];
+
+///实体, 读取时缺少的键以默认值补全, 值未被修改时不会写回.
+///新建的实体中`Attributes`, `Armor`, `Mainhand`与`Offhand`为空时不会写入
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub identifier: String,
+    ///世界坐标`Pos`
+    pub pos: [f32; 3],
+    ///偏航角与俯仰角
+    pub rotation: [f32; 2],
+    pub motion: [f32; 3],
+    pub unique_id: i64,
+    pub attributes: Vec<Attribute>,
+    ///头盔, 胸甲, 护腿, 靴子
+    pub armor: Vec<ItemStack>,
+    pub mainhand: Vec<ItemStack>,
+    pub offhand: Vec<ItemStack>,
+    ///实体定义中启用的组件组, 如`+minecraft:pig_adult`
+    pub definitions: Vec<String>,
+    ///未被识别的键
+    pub other: Map<String, Value>,
+    ///读取时缺少而以默认值补全的键, 值未被修改时不会写回
+    pub filled: Map<String, Value>,
+}
+
+///`Attributes`中的一项, 如`minecraft:health`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub base: f32,
+    pub current: f32,
+    pub min: f32,
+    pub max: f32,
+    pub default_min: f32,
+    pub default_max: f32,
+    ///`Modifiers`等未被识别的键
+    pub other: Map<String, Value>,
+}
+
+impl Entity {
+    pub fn new(identifier: &str) -> Self {
+        Entity {
+            identifier: identifier.to_owned(),
+            pos: [0.0; 3],
+            rotation: [0.0; 2],
+            motion: [0.0; 3],
+            unique_id: 0,
+            attributes: Vec::new(),
+            armor: Vec::new(),
+            mainhand: Vec::new(),
+            offhand: Vec::new(),
+            definitions: vec![format!("+{identifier}")],
+            other: Map::new(),
+            filled: ["Armor", "Attributes", "Mainhand", "Offhand"]
+                .into_iter()
+                .map(|k| (k.to_owned(), Value::List(Vec::new())))
+                .collect(),
+        }
+    }
+
+    ///平移实体的坐标
+    pub fn translate(&mut self, offset: [f64; 3]) {
+        for (p, d) in self.pos.iter_mut().zip(offset) {
+            *p = (*p as f64 + d) as f32;
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    pub fn attribute_mut(&mut self, name: &str) -> Option<&mut Attribute> {
+        self.attributes.iter_mut().find(|a| a.name == name)
+    }
+}
+
+impl NbtCompound for Entity {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        let keys = map.keys().cloned().collect::<Vec<_>>();
+        let mut entity = Entity {
+            identifier: take(map, "identifier")?,
+            pos: read_or(map, "Pos")?,
+            rotation: read_or(map, "Rotation")?,
+            motion: read_or(map, "Motion")?,
+            unique_id: read_or(map, "UniqueID")?,
+            attributes: read_or(map, "Attributes")?,
+            armor: read_or(map, "Armor")?,
+            mainhand: read_or(map, "Mainhand")?,
+            offhand: read_or(map, "Offhand")?,
+            definitions: read_or(map, "definitions")?,
+            other: std::mem::take(map),
+            filled: Map::new(),
+        };
+        entity.filled = filled_keys(&keys, entity.to_compound());
+        Ok(entity)
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "identifier", &self.identifier);
+        put(map, "Pos", &self.pos);
+        put(map, "Rotation", &self.rotation);
+        put(map, "Motion", &self.motion);
+        put(map, "UniqueID", &self.unique_id);
+        put(map, "Armor", &self.armor);
+        put(map, "Mainhand", &self.mainhand);
+        put(map, "Offhand", &self.offhand);
+        put(map, "Attributes", &self.attributes);
+        put(map, "definitions", &self.definitions);
+        map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        sort_keys(map);
+        drop_filled(map, &self.filled);
+    }
+}
+
+impl NbtValue for Entity {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
+
+impl NbtCompound for Attribute {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        Ok(Attribute {
+            name: take(map, "Name")?,
+            base: read_or(map, "Base")?,
+            current: read_or(map, "Current")?,
+            min: read_or(map, "Min")?,
+            max: read_or(map, "Max")?,
+            default_min: read_or(map, "DefaultMin")?,
+            default_max: read_or(map, "DefaultMax")?,
+            other: std::mem::take(map),
+        })
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Base", &self.base);
+        put(map, "Current", &self.current);
+        put(map, "DefaultMax", &self.default_max);
+        put(map, "DefaultMin", &self.default_min);
+        put(map, "Max", &self.max);
+        put(map, "Min", &self.min);
+        put(map, "Name", &self.name);
+        map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        sort_keys(map);
+    }
+}
+
+impl NbtValue for Attribute {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
+
+///将引用旧`UniqueID`的键改为新的值
+struct Remap<'a>(&'a HashMap<i64, i64>);
+
+impl VisitMut for Remap<'_> {
+    fn visit_compound_mut(&mut self, path: &str, map: &mut Map<String, Value>) {
+        for key in REFERENCE_KEYS {
+            if let Some(Value::Long(id)) = map.get_mut(key) {
+                if let Some(&new) = self.0.get(id) {
+                    *id = new;
+                }
+            }
+        }
+        walk_compound_mut(self, path, map);
+    }
+}
+
+impl McStructure {
+    ///第`index`个实体, 不存在时返回`None`
+    pub fn entity(&self, index: usize) -> IResult<Option<Entity>> {
+        self.entities
+            .get(index)
+            .map(|map| Entity::from_compound(map.clone()))
+            .transpose()
+            .map_err(|e| e.at(&format!("structure/entities/{index}")))
+    }
+
+    pub fn add_entity(&mut self, entity: &Entity) {
+        self.entities.push(entity.to_compound());
+    }
+
+    ///为所有实体生成新的`UniqueID`, 并修改实体之间的引用(拴绳, 骑乘, 主人等),
+    ///在将实体复制到其他结构或同一结构中多次粘贴之前使用
+    pub fn regenerate_unique_ids(&mut self) {
+        let state = RandomState::new();
+        let mut used = HashSet::new();
+        let mut remap = HashMap::new();
+        let mut counter = 0u64;
+        for entity in &mut self.entities {
+            let id = loop {
+                let mut hasher = state.build_hasher();
+                hasher.write_u64(counter);
+                counter += 1;
+                //基岩版保存的`UniqueID`都是负数
+                let id = (hasher.finish() | 1 << 63) as i64;
+                if id != -1 && used.insert(id) {
+                    break id;
+                }
+            };
+            if let Some(Value::Long(old)) = entity.get_mut("UniqueID") {
+                remap.insert(*old, id);
+                *old = id;
+            } else {
+                entity.insert("UniqueID".to_owned(), Value::Long(id));
+                sort_keys(entity);
+            }
+        }
+        let mut visitor = Remap(&remap);
+        for entity in &mut self.entities {
+            visitor.visit_compound_mut("", entity);
+        }
+    }
+}
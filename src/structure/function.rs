@@ -0,0 +1,380 @@
+//! 结构与`.mcfunction`中`setblock`/`fill`命令的相互转换
+use super::{index_of, BlockPattern, BlockState, McStructure};
+use crate::{Error, IResult, Value};
+
+///单个函数文件中命令数量的上限
+pub const MAX_COMMANDS: usize = 10000;
+///单条`fill`命令能填充的方块数上限
+pub const MAX_FILL_VOLUME: usize = 32768;
+
+///[`McStructure::to_commands`]的选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    ///`None`时使用以执行位置为原点的相对坐标`~x ~y ~z`, 否则使用以此为原点的绝对坐标
+    pub origin: Option<[i32; 3]>,
+    ///是否输出空气, 结构方块加载结构时空气会覆盖原有的方块
+    pub include_air: bool,
+    ///每个函数文件的命令数上限
+    pub max_commands: usize,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            origin: None,
+            include_air: true,
+            max_commands: MAX_COMMANDS,
+        }
+    }
+}
+
+///命令中的方块参数, 例如`minecraft:observer ["facing_direction"=3,"powered_bit"=false]`
+///
+///只有旧版本数据值`val`而没有方块状态的调色板项输出为`minecraft:wool 14`
+pub fn block_argument(block: &BlockState) -> String {
+    let mut out = block.name.clone();
+    if block.states.is_empty() {
+        if let Some(val) = block.other.get("val").and_then(Value::to_i64) {
+            out.push_str(&format!(" {val}"));
+        }
+        return out;
+    }
+    out.push_str(" [");
+    for (index, (k, v)) in block.states.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let v = match v {
+            Value::Byte(0) => "false".to_owned(),
+            Value::Byte(1) => "true".to_owned(),
+            Value::String(s) => quote(s),
+            v => v.to_string(),
+        };
+        out.push_str(&format!("{}={v}", quote(k)));
+    }
+    out.push(']');
+    out
+}
+
+///加上双引号, 并转义其中的`"`与`\`
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///以执行位置为原点的相对坐标, 例如`~ ~1 ~-2`
+pub(crate) fn relative_coords(pos: [i32; 3]) -> String {
+    pos.map(|v| match v {
+        0 => "~".to_owned(),
+        v => format!("~{v}"),
+    })
+    .join(" ")
+}
+
+impl McStructure {
+    ///将主层转换为`setblock`/`fill`命令, 相同方块组成的长方体会合并为一条`fill`命令
+    ///
+    ///命令从下往上排列, 以便沙子等受重力影响的方块有所支撑.
+    ///次层, `block_position_data`与实体无法用这两种命令表示, 会被忽略
+    pub fn to_commands(&self, options: &ExportOptions) -> Vec<String> {
+        let [sx, sy, sz] = self.size.map(|v| v.max(0));
+        let skip = self
+            .block_palette
+            .iter()
+            .map(|b| !options.include_air && b.is_air())
+            .collect::<Vec<_>>();
+        let block_at = |x: i32, y: i32, z: i32| {
+            let i = self.block_indices[0][self.index(x, y, z).unwrap()];
+            match usize::try_from(i) {
+                Ok(i) if i < skip.len() && !skip[i] => Some(i),
+                _ => None,
+            }
+        };
+        let coord = |pos: [i32; 3]| match options.origin {
+            Some(origin) => {
+                let [x, y, z] = [0, 1, 2].map(|i| origin[i] + pos[i]);
+                format!("{x} {y} {z}")
+            }
+            None => relative_coords(pos),
+        };
+
+        let mut done = vec![false; self.volume()];
+        let mut commands = Vec::new();
+        for y in 0..sy {
+            for x in 0..sx {
+                for z in 0..sz {
+                    let index = self.index(x, y, z).unwrap();
+                    let Some(block) = block_at(x, y, z).filter(|_| !done[index]) else {
+                        continue;
+                    };
+                    let same = |x: i32, y: i32, z: i32| {
+                        !done[self.index(x, y, z).unwrap()] && block_at(x, y, z) == Some(block)
+                    };
+                    let fits =
+                        |size: [i32; 3]| size.iter().product::<i32>() as usize <= MAX_FILL_VOLUME;
+
+                    let mut end = [x, y, z];
+                    while end[2] + 1 < sz && fits([1, 1, end[2] + 2 - z]) && same(x, y, end[2] + 1)
+                    {
+                        end[2] += 1;
+                    }
+                    while end[0] + 1 < sx
+                        && fits([end[0] + 2 - x, 1, end[2] + 1 - z])
+                        && (z..=end[2]).all(|z| same(end[0] + 1, y, z))
+                    {
+                        end[0] += 1;
+                    }
+                    while end[1] + 1 < sy
+                        && fits([end[0] + 1 - x, end[1] + 2 - y, end[2] + 1 - z])
+                        && (x..=end[0]).all(|x| (z..=end[2]).all(|z| same(x, end[1] + 1, z)))
+                    {
+                        end[1] += 1;
+                    }
+
+                    for x in x..=end[0] {
+                        for y in y..=end[1] {
+                            for z in z..=end[2] {
+                                done[self.index(x, y, z).unwrap()] = true;
+                            }
+                        }
+                    }
+                    let block = block_argument(&self.block_palette[block]);
+                    commands.push(match end == [x, y, z] {
+                        true => format!("setblock {} {block}", coord([x, y, z])),
+                        false => format!("fill {} {} {block}", coord([x, y, z]), coord(end)),
+                    });
+                }
+            }
+        }
+        commands
+    }
+
+    ///[`Self::to_commands`]的结果按`max_commands`拆分为多个函数文件的内容
+    pub fn to_mcfunctions(&self, options: &ExportOptions) -> Vec<String> {
+        self.to_commands(options)
+            .chunks(options.max_commands.max(1))
+            .map(|chunk| chunk.join("\n") + "\n")
+            .collect()
+    }
+}
+
+///`fill`/`setblock`命令的模式
+#[derive(Debug)]
+enum Mode {
+    Replace,
+    Keep,
+    Hollow,
+    Outline,
+    Filter(BlockPattern),
+}
+
+#[derive(Debug)]
+struct Command {
+    from: [i32; 3],
+    to: [i32; 3],
+    ///`from`与`to`各坐标是否为相对坐标
+    relative: [bool; 6],
+    block: BlockState,
+    mode: Mode,
+}
+
+///按空白拆分, 方括号与双引号中的空白不拆分, 双引号中的`\\`为转义, 以`[`开头的部分合并到前一项
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '[' if !quoted => depth += 1,
+            ']' if !quoted => depth -= 1,
+            c if c.is_whitespace() && !quoted && depth <= 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if c == '[' && current.is_empty() && depth == 1 {
+            if let Some(last) = tokens.pop() {
+                current = last;
+            }
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+///返回坐标与是否为相对坐标, 相对坐标以执行位置为0
+fn parse_coord(s: &str) -> Option<(i32, bool)> {
+    let (relative, s) = match s.strip_prefix('~') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    match (relative, s) {
+        (true, "") => Some((0, true)),
+        _ => s
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(|v| (v.floor() as i32, relative)),
+    }
+}
+
+fn parse_command(line: &str) -> IResult<Option<Command>> {
+    let tokens = tokenize(line.trim().trim_start_matches('/'));
+    let syntax = || Error::Syntax(format!("无效的命令: \"{}\"", line.trim()));
+    let count = match tokens.first().map(String::as_str) {
+        Some("setblock") => 3,
+        Some("fill") => 6,
+        _ => return Ok(None),
+    };
+    let mut rest = tokens[1..].iter().map(String::as_str).peekable();
+    let mut coords = [0; 6];
+    let mut relative = [false; 6];
+    for i in 0..count {
+        (coords[i], relative[i]) = rest.next().and_then(parse_coord).ok_or_else(syntax)?;
+    }
+    if count == 3 {
+        coords.copy_within(0..3, 3);
+        relative.copy_within(0..3, 3);
+    }
+    let from = [coords[0], coords[1], coords[2]];
+    let to = [coords[3], coords[4], coords[5]];
+    let volume = (0..3)
+        .map(|i| (from[i] as i64 - to[i] as i64).abs() + 1)
+        .product::<i64>();
+    if volume > MAX_FILL_VOLUME as i64 {
+        return Err(Error::Syntax(format!(
+            "fill命令的方块数{volume}超过上限{MAX_FILL_VOLUME}: \"{}\"",
+            line.trim()
+        )));
+    }
+
+    let mut block: BlockState = rest.next().ok_or_else(syntax)?.parse()?;
+    //旧版本命令中的数据值, 保存为旧版调色板的`val`
+    if let Some(data) = rest.next_if(|s| s.parse::<i16>().is_ok()) {
+        let data = data.parse::<i16>().unwrap();
+        if data != 0 {
+            block.version = None;
+            block.other.insert("val".to_owned(), Value::Short(data));
+        }
+    }
+
+    let mode = match rest.next() {
+        None | Some("replace") | Some("destroy") => match rest.next() {
+            Some(filter) => Mode::Filter(filter.parse()?),
+            None => Mode::Replace,
+        },
+        Some("keep") => Mode::Keep,
+        Some("hollow") if count == 6 => Mode::Hollow,
+        Some("outline") if count == 6 => Mode::Outline,
+        Some(_) => return Err(syntax()),
+    };
+    Ok(Some(Command {
+        from,
+        to,
+        relative,
+        block,
+        mode,
+    }))
+}
+
+impl McStructure {
+    ///由`.mcfunction`中的`setblock`/`fill`命令构造结构, 其他命令与注释会被忽略
+    ///
+    ///相对坐标`~`以执行位置为原点, 同一坐标轴上不能混用相对坐标与绝对坐标,
+    ///不支持局部坐标`^`, 方块数超过[`MAX_FILL_VOLUME`]的`fill`命令会返回错误.
+    ///结构的大小为所有命令的包围盒, `structure_world_origin`为包围盒的最小角,
+    ///未被命令覆盖的位置为structure_void. 旧版本的数据值会保存为调色板中的`val`
+    pub fn from_commands(src: &str) -> IResult<McStructure> {
+        let mut commands = Vec::new();
+        //每个坐标轴使用的是否为相对坐标
+        let mut kinds = [None; 3];
+        for (line, text) in src.lines().enumerate() {
+            if text.trim_start().starts_with('#') {
+                continue;
+            }
+            let error = |e| Error::Syntax(format!("第{}行: {e}", line + 1));
+            let Some(command) = parse_command(text).map_err(error)? else {
+                continue;
+            };
+            for (i, &relative) in command.relative.iter().enumerate() {
+                if *kinds[i % 3].get_or_insert(relative) != relative {
+                    return Err(error(Error::Syntax(format!(
+                        "第{}个坐标轴混用了相对坐标与绝对坐标",
+                        i % 3 + 1
+                    ))));
+                }
+            }
+            commands.push(command);
+        }
+
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for c in &commands {
+            for i in 0..3 {
+                min[i] = min[i].min(c.from[i]).min(c.to[i]);
+                max[i] = max[i].max(c.from[i]).max(c.to[i]);
+            }
+        }
+        if commands.is_empty() {
+            return Ok(McStructure::new([0; 3]));
+        }
+        let size = [0, 1, 2].map(|i| max[i] as i64 - min[i] as i64 + 1);
+        if size.iter().any(|&v| v > i32::MAX as i64) {
+            return Err(Error::Syntax(format!("命令的范围过大: {size:?}")));
+        }
+        let mut structure = McStructure::new(size.map(|v| v as i32));
+        structure.structure_world_origin = min;
+
+        for c in commands {
+            let from = [0, 1, 2].map(|i| c.from[i] - min[i]);
+            let to = [0, 1, 2].map(|i| c.to[i] - min[i]);
+            match c.mode {
+                Mode::Replace => {
+                    structure.fill(from, to, &c.block);
+                }
+                Mode::Hollow => {
+                    structure.hollow(from, to, &c.block);
+                }
+                Mode::Outline => {
+                    structure.outline(from, to, &c.block);
+                }
+                Mode::Keep | Mode::Filter(_) => {
+                    let keep = |b: Option<&BlockState>| match &c.mode {
+                        Mode::Filter(pattern) => b.is_some_and(|b| pattern.matches(b)),
+                        _ => b.is_none_or(BlockState::is_air),
+                    };
+                    //只检查命令的区域, 先记录所有位置再修改
+                    let (lo, hi) = (
+                        [0, 1, 2].map(|i| from[i].min(to[i])),
+                        [0, 1, 2].map(|i| from[i].max(to[i])),
+                    );
+                    let mut matched = Vec::new();
+                    for x in lo[0]..=hi[0] {
+                        for y in lo[1]..=hi[1] {
+                            for z in lo[2]..=hi[2] {
+                                let index = index_of(structure.size, [x, y, z]);
+                                let palette = structure.block_indices[0][index];
+                                if keep(structure.palette_entry(palette)) {
+                                    matched.push(index);
+                                }
+                            }
+                        }
+                    }
+                    let palette = structure.palette_index(&c.block);
+                    for index in matched {
+                        structure.set_index(index, palette);
+                    }
+                }
+            }
+        }
+        Ok(structure)
+    }
+}
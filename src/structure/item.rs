@@ -0,0 +1,249 @@
+//! 容器与实体中的物品
+//!
+//! 与方块实体相同, 写回时键按字节序排列, 游戏生成的物品可以原样写回
+use super::BlockState;
+use crate::compound::{drop_filled, filled_keys, put, sort_keys, take};
+use crate::{__private::read, IResult, Map, NbtCompound, NbtValue, Value};
+
+///`Items`, `Armor`等列表中的一个物品
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub name: String,
+    pub count: i8,
+    pub damage: i16,
+    ///在容器中的位置, 手持物品与盔甲没有这个键
+    pub slot: Option<i8>,
+    pub was_picked_up: bool,
+    ///方块物品对应的方块
+    pub block: Option<BlockState>,
+    ///附魔, 自定义名称, 潜影盒中的物品等
+    pub tag: Option<Map<String, Value>>,
+    ///未被识别的键
+    pub other: Map<String, Value>,
+    ///读取时缺少而以默认值补全的键, 如没有耐久的物品的`Damage`, 值未被修改时不会写回
+    pub filled: Map<String, Value>,
+}
+
+///`tag.ench`中的一项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Enchantment {
+    pub id: i16,
+    pub lvl: i16,
+}
+
+impl ItemStack {
+    pub fn new(name: &str, count: i8) -> Self {
+        ItemStack {
+            name: name.to_owned(),
+            count,
+            damage: 0,
+            slot: None,
+            was_picked_up: false,
+            block: None,
+            tag: None,
+            other: Map::new(),
+            filled: Map::new(),
+        }
+    }
+
+    ///`Name`为空字符串的物品表示空槽位
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() || self.count <= 0
+    }
+
+    fn tag_mut(&mut self) -> &mut Map<String, Value> {
+        self.tag.get_or_insert_with(Map::new)
+    }
+
+    ///移除空的`display`与`tag`
+    fn tidy(&mut self) {
+        if let Some(tag) = &mut self.tag {
+            if tag
+                .get("display")
+                .and_then(Value::as_compound)
+                .is_some_and(Map::is_empty)
+            {
+                tag.remove("display");
+            }
+            if tag.is_empty() {
+                self.tag = None;
+            }
+        }
+    }
+
+    fn display(&self) -> Option<&Map<String, Value>> {
+        self.tag.as_ref()?.get("display")?.as_compound()
+    }
+
+    fn display_mut(&mut self) -> &mut Map<String, Value> {
+        let tag = self.tag_mut();
+        if !matches!(tag.get("display"), Some(Value::Compound(_))) {
+            tag.insert("display".to_owned(), Value::Compound(Map::new()));
+        }
+        tag.get_mut("display")
+            .and_then(Value::as_compound_mut)
+            .unwrap()
+    }
+
+    pub fn enchantments(&self) -> Vec<Enchantment> {
+        let Some(list) = self.tag.as_ref().and_then(|t| t.get("ench")?.as_list()) else {
+            return Vec::new();
+        };
+        list.iter()
+            .filter_map(|e| {
+                Some(Enchantment {
+                    id: e.get("id")?.to_i16()?,
+                    lvl: e.get("lvl")?.to_i16()?,
+                })
+            })
+            .collect()
+    }
+
+    ///设置附魔等级, 已有同一附魔时替换其等级
+    pub fn set_enchantment(&mut self, id: i16, lvl: i16) {
+        let mut list = self.enchantments();
+        match list.iter_mut().find(|e| e.id == id) {
+            Some(e) => e.lvl = lvl,
+            None => list.push(Enchantment { id, lvl }),
+        }
+        self.set_enchantments(&list);
+    }
+
+    ///移除附魔, 返回是否存在该附魔
+    pub fn remove_enchantment(&mut self, id: i16) -> bool {
+        let mut list = self.enchantments();
+        let len = list.len();
+        list.retain(|e| e.id != id);
+        if list.len() == len {
+            return false;
+        }
+        self.set_enchantments(&list);
+        true
+    }
+
+    fn set_enchantments(&mut self, list: &[Enchantment]) {
+        if list.is_empty() {
+            if let Some(tag) = &mut self.tag {
+                tag.remove("ench");
+            }
+            return self.tidy();
+        }
+        let list = list
+            .iter()
+            .map(|e| {
+                let mut map = Map::new();
+                put(&mut map, "id", &e.id);
+                put(&mut map, "lvl", &e.lvl);
+                Value::Compound(map)
+            })
+            .collect();
+        self.tag_mut().insert("ench".to_owned(), Value::List(list));
+    }
+
+    ///`tag.display.Name`
+    pub fn custom_name(&self) -> Option<&str> {
+        self.display()?.get("Name")?.as_str()
+    }
+
+    pub fn set_custom_name(&mut self, name: Option<&str>) {
+        match name {
+            Some(name) => put(self.display_mut(), "Name", &name.to_owned()),
+            None => {
+                self.display_mut().remove("Name");
+                self.tidy();
+            }
+        }
+    }
+
+    ///`tag.display.Lore`, 每一项为一行
+    pub fn lore(&self) -> Vec<String> {
+        self.display()
+            .and_then(|d| d.get("Lore")?.as_list())
+            .map(|l| {
+                l.iter()
+                    .filter_map(|v| v.as_str().map(str::to_owned))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    ///设置`tag.display.Lore`, 为空时移除
+    pub fn set_lore<S: AsRef<str>>(&mut self, lore: &[S]) {
+        if lore.is_empty() {
+            self.display_mut().remove("Lore");
+            return self.tidy();
+        }
+        let lore = lore.iter().map(|s| Value::from(s.as_ref())).collect();
+        self.display_mut()
+            .insert("Lore".to_owned(), Value::List(lore));
+    }
+
+    ///潜影盒等物品中保存的物品`tag.Items`
+    pub fn contents(&self) -> IResult<Vec<ItemStack>> {
+        match self.tag.as_ref().and_then(|t| t.get("Items")) {
+            Some(v) => Vec::from_value(v.clone()).map_err(|e| e.at("tag/Items")),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    ///设置`tag.Items`, 为空时移除
+    pub fn set_contents(&mut self, items: &[ItemStack]) {
+        if items.is_empty() {
+            if let Some(tag) = &mut self.tag {
+                tag.remove("Items");
+            }
+            return self.tidy();
+        }
+        put(self.tag_mut(), "Items", &items.to_vec());
+    }
+}
+
+impl NbtCompound for ItemStack {
+    fn read_compound(map: &mut Map<String, Value>) -> IResult<Self> {
+        let keys = map.keys().cloned().collect::<Vec<_>>();
+        let mut item = ItemStack {
+            name: take(map, "Name")?,
+            count: read(map, "Count")?.unwrap_or_default(),
+            damage: read(map, "Damage")?.unwrap_or_default(),
+            slot: read(map, "Slot")?,
+            was_picked_up: read(map, "WasPickedUp")?.unwrap_or_default(),
+            block: read(map, "Block")?,
+            tag: read(map, "tag")?,
+            other: std::mem::take(map),
+            filled: Map::new(),
+        };
+        item.filled = filled_keys(&keys, item.to_compound());
+        Ok(item)
+    }
+
+    fn write_compound(&self, map: &mut Map<String, Value>) {
+        put(map, "Count", &self.count);
+        put(map, "Damage", &self.damage);
+        put(map, "Name", &self.name);
+        if let Some(slot) = self.slot {
+            put(map, "Slot", &slot);
+        }
+        put(map, "WasPickedUp", &self.was_picked_up);
+        if let Some(block) = &self.block {
+            put(map, "Block", block);
+        }
+        if let Some(tag) = &self.tag {
+            put(map, "tag", tag);
+        }
+        map.extend(self.other.iter().map(|(k, v)| (k.clone(), v.clone())));
+        sort_keys(map);
+        drop_filled(map, &self.filled);
+    }
+}
+
+impl NbtValue for ItemStack {
+    const TAG: u8 = 0x0a;
+
+    fn from_value(v: Value) -> IResult<Self> {
+        Self::from_compound(crate::__private::compound(v)?)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Compound(self.to_compound())
+    }
+}
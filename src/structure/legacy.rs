@@ -0,0 +1,129 @@
+//! 将旧版本调色板中的数据值`val`升级为方块状态
+//!
+//! 映射表见`legacy.txt`, 只包含数据值与方块状态一一对应的常见方块
+use super::{BlockState, McStructure};
+use crate::{Map, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Clone)]
+enum Kind {
+    Enum(Vec<&'static str>),
+    Int,
+    Bool,
+}
+
+///映射表中的一个状态, 由`val`的第`lo`到`hi`位得到
+#[derive(Clone)]
+struct Field {
+    key: &'static str,
+    kind: Kind,
+    lo: u32,
+    hi: u32,
+}
+
+fn table() -> &'static HashMap<String, Vec<Field>> {
+    static TABLE: OnceLock<HashMap<String, Vec<Field>>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+        for line in include_str!("legacy.txt").lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let names = parts.next().unwrap();
+            let fields = parts.map(parse_field).collect::<Vec<_>>();
+            for name in names.split('|') {
+                table.insert(format!("minecraft:{name}"), fields.clone());
+            }
+        }
+        table
+    })
+}
+
+fn parse_field(spec: &'static str) -> Field {
+    let (key, bits) = spec.split_once('@').expect("legacy.txt");
+    let (lo, hi) = match bits.split_once('-') {
+        Some((lo, hi)) => (lo.parse().unwrap(), hi.parse().unwrap()),
+        None => (bits.parse().unwrap(), bits.parse().unwrap()),
+    };
+    let (key, kind) = match key.split_once('=') {
+        Some((key, values)) => (key, Kind::Enum(values.split(',').collect())),
+        None => match key.split_once(':') {
+            Some((key, "int")) => (key, Kind::Int),
+            Some((key, "bool")) => (key, Kind::Bool),
+            _ => panic!("legacy.txt: {spec}"),
+        },
+    };
+    Field { key, kind, lo, hi }
+}
+
+///[`McStructure::upgrade_legacy`]的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpgradeReport {
+    ///升级成功的调色板项数
+    pub upgraded: usize,
+    ///映射表中不存在或数据值无效而未升级的方块名称与数据值
+    pub unknown: Vec<(String, i32)>,
+}
+
+impl BlockState {
+    ///升级后使用的版本号, 即1.16.0.14, 映射表中的状态与这一版本一致,
+    ///游戏加载时会继续将其升级到当前版本的方块名称与状态
+    pub const LEGACY_UPGRADE_VERSION: i32 = 0x01_10_00_0E;
+
+    ///是否是使用数据值`val`的旧版本调色板项
+    pub fn is_legacy(&self) -> bool {
+        self.other.contains_key("val")
+    }
+
+    ///将数据值`val`转换为方块状态, 不是旧版本的调色板项或无法转换时返回`false`且不做修改
+    pub fn upgrade_legacy(&mut self) -> bool {
+        let Some(val) = self.other.get("val").and_then(Value::to_i32) else {
+            return false;
+        };
+        let mut states = Map::new();
+        match table().get(&self.name) {
+            Some(fields) => {
+                for field in fields {
+                    let mask = (1 << (field.hi - field.lo + 1)) - 1;
+                    let v = (val >> field.lo) & mask;
+                    let value = match &field.kind {
+                        Kind::Enum(values) => match values.get(v as usize) {
+                            Some(s) => Value::String(s.to_string()),
+                            None => return false,
+                        },
+                        Kind::Int => Value::Int(v),
+                        Kind::Bool => Value::Byte(v as i8),
+                    };
+                    states.insert(field.key.to_owned(), value);
+                }
+            }
+            //没有状态的方块只有数据值0
+            None if val == 0 => {}
+            None => return false,
+        }
+        self.other.remove("val");
+        self.states = states;
+        self.version = Some(BlockState::LEGACY_UPGRADE_VERSION);
+        true
+    }
+}
+
+impl McStructure {
+    ///升级调色板中所有使用数据值的项, 升级后可能出现重复的项, 可以随后调用[`Self::compact_palette`]
+    pub fn upgrade_legacy(&mut self) -> UpgradeReport {
+        let mut report = UpgradeReport::default();
+        for block in self.block_palette.iter_mut().filter(|b| b.is_legacy()) {
+            match block.upgrade_legacy() {
+                true => report.upgraded += 1,
+                false => {
+                    let val = block.other.get("val").and_then(Value::to_i32).unwrap_or(0);
+                    report.unknown.push((block.name.clone(), val));
+                }
+            }
+        }
+        report
+    }
+}
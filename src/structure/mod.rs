@@ -0,0 +1,348 @@
+//! `.mcstructure`结构文件
+//!
+//! # Example
+//!
+//! ```
+//! use nbtrock::{structure::McStructure, IResult};
+//! use std::fs::File;
+//! fn read_structure() -> IResult<()> {
+//!     let s = McStructure::read(&mut File::open("res/gold_farm.mcstructure")?)?;
+//!     println!("{:?} {}", s.size, s.block_palette.len());
+//!     Ok(())
+//! }
+//! ```
+mod block;
+mod block_entity;
+mod command;
+mod diff;
+mod entity;
+mod function;
+mod item;
+mod legacy;
+mod palette;
+mod paste;
+mod resize;
+mod schema;
+mod search;
+mod shape;
+mod stats;
+mod tile;
+mod transform;
+mod validate;
+
+pub use block::BlockState;
+pub use block_entity::{
+    Barrel, Beacon, BlockEntity, BlockEntityKind, Chest, ChestPair, CommandBlock, Furnace, Hopper,
+    Sign, SignText,
+};
+pub use command::{
+    rewrite_world_commands, world_command_blocks, CommandBlockInfo, CommandBlockMode,
+};
+pub use diff::{BlockDiff, DataDiff, StructureDiff};
+pub use entity::{Attribute, Entity};
+pub use function::{block_argument, ExportOptions, MAX_COMMANDS, MAX_FILL_VOLUME};
+pub use item::{Enchantment, ItemStack};
+pub use legacy::UpgradeReport;
+pub use palette::CompactReport;
+pub use paste::PasteMode;
+pub use resize::Padding;
+pub use schema::StateType;
+pub use search::BlockPattern;
+pub use stats::Materials;
+pub use tile::{Tile, TileSet, MAX_TILE_SIZE};
+pub use transform::{Mirror, Rotation};
+pub use validate::{Issue, Problem};
+
+use crate::{
+    compound::{sort_keys, take},
+    Error, IResult, Map, NbtCompound, NbtValue, Value, NBT,
+};
+use std::io::{Read, Write};
+
+///`.mcstructure`文件的内容
+///
+///`block_indices`中的值是`block_palette`的下标, -1表示该位置没有方块(structure_void),
+///`block_position_data`的键是方块在`block_indices`中的下标
+#[derive(Debug, Clone, PartialEq)]
+pub struct McStructure {
+    ///根标签的名称, 通常为空字符串
+    pub name: String,
+    pub format_version: i32,
+    pub size: [i32; 3],
+    pub structure_world_origin: [i32; 3],
+    ///主层与次层(通常用于含水方块)
+    pub block_indices: [Vec<i32>; 2],
+    pub entities: Vec<Map<String, Value>>,
+    ///`palette.default.block_palette`
+    pub block_palette: Vec<BlockState>,
+    ///`palette.default.block_position_data`
+    pub block_position_data: Map<i32, Map<String, Value>>,
+    ///未被识别的键, 写回时原样保留
+    pub unknown: Unknown,
+}
+
+///[`McStructure`]各层级中未被识别的键
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unknown {
+    pub root: Map<String, Value>,
+    pub structure: Map<String, Value>,
+    ///`palette`中除`default`外的调色板
+    pub palette: Map<String, Value>,
+    pub default_palette: Map<String, Value>,
+    ///读取的文件中没有`block_position_data`, 此时`block_position_data`为空则不写入该键
+    pub no_block_position_data: bool,
+}
+
+impl McStructure {
+    ///新建一个由`structure_void`填充的结构
+    pub fn new(size: [i32; 3]) -> Self {
+        let volume = size.iter().map(|&v| v.max(0) as usize).product();
+        McStructure {
+            name: String::new(),
+            format_version: 1,
+            size,
+            structure_world_origin: [0, 0, 0],
+            block_indices: [vec![-1; volume], vec![-1; volume]],
+            entities: Vec::new(),
+            block_palette: Vec::new(),
+            block_position_data: Map::new(),
+            unknown: Unknown::default(),
+        }
+    }
+
+    ///方块总数
+    pub fn volume(&self) -> usize {
+        self.size.iter().map(|&v| v.max(0) as usize).product()
+    }
+
+    ///返回相对坐标在`block_indices`中的下标, 顺序为x, y, z, 其中z变化最快
+    pub fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let [sx, sy, sz] = self.size;
+        if !(0..sx).contains(&x) || !(0..sy).contains(&y) || !(0..sz).contains(&z) {
+            return None;
+        }
+        Some(index_of(self.size, [x, y, z]))
+    }
+
+    ///[`Self::index`]的逆运算
+    pub fn position(&self, index: usize) -> [i32; 3] {
+        position_of(self.size, index)
+    }
+
+    fn checked_index(&self, x: i32, y: i32, z: i32) -> IResult<usize> {
+        self.index(x, y, z).ok_or(Error::OutOfBounds([x, y, z]))
+    }
+
+    fn palette_entry(&self, index: i32) -> Option<&BlockState> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.block_palette.get(i))
+    }
+
+    ///主层中的方块, 坐标越界或为structure_void时返回`None`
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<&BlockState> {
+        self.palette_entry(self.block_indices[0][self.index(x, y, z)?])
+    }
+
+    ///次层中的方块, 通常是含水方块中的水
+    pub fn get_secondary_block(&self, x: i32, y: i32, z: i32) -> Option<&BlockState> {
+        self.palette_entry(self.block_indices[1][self.index(x, y, z)?])
+    }
+
+    ///在调色板中查找相同的方块, 不存在时添加到调色板末尾, 返回其下标
+    pub fn palette_index(&mut self, block: &BlockState) -> i32 {
+        match self.block_palette.iter().position(|b| b.same_block(block)) {
+            Some(i) => i as i32,
+            None => {
+                self.block_palette.push(block.clone());
+                self.block_palette.len() as i32 - 1
+            }
+        }
+    }
+
+    ///设置主层中的方块
+    ///
+    ///次层会被清空, 如需保留含水状态请随后调用[`Self::set_secondary_block`].
+    ///如果方块发生了变化, 该位置的`block_position_data`会被移除
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockState) -> IResult<()> {
+        let index = self.checked_index(x, y, z)?;
+        let palette = self.palette_index(&block);
+        self.set_index(index, palette);
+        Ok(())
+    }
+
+    ///设置次层中的方块, `None`表示清空
+    pub fn set_secondary_block(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: Option<BlockState>,
+    ) -> IResult<()> {
+        let index = self.checked_index(x, y, z)?;
+        self.block_indices[1][index] = match block {
+            Some(block) => self.palette_index(&block),
+            None => -1,
+        };
+        Ok(())
+    }
+
+    ///将该位置设置为structure_void
+    pub fn remove_block(&mut self, x: i32, y: i32, z: i32) -> IResult<()> {
+        let index = self.checked_index(x, y, z)?;
+        self.set_index(index, -1);
+        Ok(())
+    }
+
+    ///以调色板下标设置主层, 同时清空次层并移除失效的`block_position_data`
+    pub(crate) fn set_index(&mut self, index: usize, palette: i32) {
+        let old = self.block_indices[0][index];
+        if old != palette {
+            self.block_position_data.remove(&(index as i32));
+        }
+        self.block_indices[0][index] = palette;
+        self.block_indices[1][index] = -1;
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> IResult<Self> {
+        McStructure::from_nbt(NBT::from_reader(r)?)
+    }
+
+    ///写入`.mcstructure`文件, 结构文件没有基岩版头部
+    pub fn write<W: Write>(&self, w: &mut W) -> IResult<()> {
+        self.to_nbt().write(w, false)
+    }
+
+    pub fn from_nbt(nbt: NBT) -> IResult<Self> {
+        let mut structure = McStructure::from_compound(crate::__private::compound(nbt.data)?)?;
+        structure.name = nbt.name;
+        Ok(structure)
+    }
+
+    pub fn to_nbt(&self) -> NBT {
+        NBT {
+            name: self.name.clone(),
+            data: Value::Compound(self.to_compound()),
+        }
+    }
+}
+
+impl NbtCompound for McStructure {
+    fn read_compound(root: &mut Map<String, Value>) -> IResult<Self> {
+        let format_version = take(root, "format_version")?;
+        let size = take(root, "size")?;
+        let mut structure: Map<String, Value> = take(root, "structure")?;
+        let structure_world_origin = take(root, "structure_world_origin")?;
+
+        let block_indices = take_in(&mut structure, "block_indices", "structure")?;
+        let entities = take_in(&mut structure, "entities", "structure")?;
+        let mut palette: Map<String, Value> = take_in(&mut structure, "palette", "structure")?;
+        let mut default: Map<String, Value> =
+            take_in(&mut palette, "default", "structure/palette")?;
+
+        let path = "structure/palette/default";
+        let block_palette = take_in(&mut default, "block_palette", path)?;
+        let mut block_position_data = Map::new();
+        let no_block_position_data = !default.contains_key("block_position_data");
+        if default.contains_key("block_position_data") {
+            let data: Map<String, Value> = take_in(&mut default, "block_position_data", path)?;
+            for (key, value) in data {
+                let path = format!("{path}/block_position_data/{key}");
+                let index = key
+                    .parse::<i32>()
+                    .map_err(|_| Error::Unknown(format!("无效的方块下标: \"{path}\"")))?;
+                let value = crate::__private::compound(value).map_err(|e| e.at(&path))?;
+                block_position_data.insert(index, value);
+            }
+        }
+
+        Ok(McStructure {
+            name: String::new(),
+            format_version,
+            size,
+            structure_world_origin,
+            block_indices,
+            entities,
+            block_palette,
+            block_position_data,
+            unknown: Unknown {
+                root: std::mem::take(root),
+                structure,
+                palette,
+                default_palette: default,
+                no_block_position_data,
+            },
+        })
+    }
+
+    fn write_compound(&self, root: &mut Map<String, Value>) {
+        let mut default = Map::new();
+        default.insert(
+            "block_palette".to_owned(),
+            Value::List(self.block_palette.iter().map(|b| b.to_value()).collect()),
+        );
+        if !(self.unknown.no_block_position_data && self.block_position_data.is_empty()) {
+            default.insert(
+                "block_position_data".to_owned(),
+                Value::Compound(
+                    self.block_position_data
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), Value::Compound(v.clone())))
+                        .collect(),
+                ),
+            );
+        }
+        extend(&mut default, &self.unknown.default_palette);
+
+        let mut palette = Map::new();
+        palette.insert("default".to_owned(), Value::Compound(default));
+        extend(&mut palette, &self.unknown.palette);
+
+        let mut structure = Map::new();
+        structure.insert("block_indices".to_owned(), self.block_indices.to_value());
+        structure.insert(
+            "entities".to_owned(),
+            Value::List(
+                self.entities
+                    .iter()
+                    .map(|e| Value::Compound(e.clone()))
+                    .collect(),
+            ),
+        );
+        structure.insert("palette".to_owned(), Value::Compound(palette));
+        extend(&mut structure, &self.unknown.structure);
+
+        root.insert("format_version".to_owned(), Value::Int(self.format_version));
+        root.insert("size".to_owned(), self.size.to_value());
+        root.insert("structure".to_owned(), Value::Compound(structure));
+        root.insert(
+            "structure_world_origin".to_owned(),
+            self.structure_world_origin.to_value(),
+        );
+        extend(root, &self.unknown.root);
+    }
+}
+
+pub(crate) fn index_of([_, sy, sz]: [i32; 3], [x, y, z]: [i32; 3]) -> usize {
+    ((x * sy + y) * sz + z) as usize
+}
+
+pub(crate) fn position_of([_, sy, sz]: [i32; 3], index: usize) -> [i32; 3] {
+    let i = index as i32;
+    [i / (sy * sz), i / sz % sy, i % sz]
+}
+
+fn take_in<T: NbtValue>(map: &mut Map<String, Value>, key: &str, parent: &str) -> IResult<T> {
+    take(map, key).map_err(|e| e.at(parent))
+}
+
+///加入未被识别的键, 并与游戏保存时一样按字节序排列
+fn extend(map: &mut Map<String, Value>, other: &Map<String, Value>) {
+    if other.is_empty() {
+        return;
+    }
+    for (k, v) in other {
+        map.insert(k.clone(), v.clone());
+    }
+    sort_keys(map);
+}
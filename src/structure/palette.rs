@@ -0,0 +1,71 @@
+//! 调色板的压缩与去重
+use super::{BlockState, McStructure};
+use crate::NbtValue;
+
+///[`McStructure::compact_palette`]的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    pub entries_before: usize,
+    pub entries_after: usize,
+    ///未被任何一层引用而被移除的项数
+    pub unused: usize,
+    ///与之前的项重复而被合并的项数
+    pub duplicates: usize,
+    ///调色板序列化后减少的字节数
+    pub bytes_saved: usize,
+}
+
+fn palette_len(palette: &[BlockState]) -> usize {
+    palette.iter().map(|b| b.to_value().encoded_len()).sum()
+}
+
+impl McStructure {
+    ///移除未被引用的调色板项, 合并名称与状态相同的项(不考虑状态的顺序),
+    ///并重新映射两层`block_indices`, 保留的项保持原有的相对顺序
+    pub fn compact_palette(&mut self) -> CompactReport {
+        let before = palette_len(&self.block_palette);
+        let mut used = vec![false; self.block_palette.len()];
+        for &i in self.block_indices.iter().flatten() {
+            if let Some(used) = usize::try_from(i).ok().and_then(|i| used.get_mut(i)) {
+                *used = true;
+            }
+        }
+
+        let mut report = CompactReport {
+            entries_before: self.block_palette.len(),
+            ..Default::default()
+        };
+        let mut palette: Vec<BlockState> = Vec::new();
+        let mut remap = vec![-1; self.block_palette.len()];
+        for (i, block) in std::mem::take(&mut self.block_palette)
+            .into_iter()
+            .enumerate()
+        {
+            if !used[i] {
+                report.unused += 1;
+                continue;
+            }
+            remap[i] = match palette.iter().position(|b| b.same_block(&block)) {
+                Some(j) => {
+                    report.duplicates += 1;
+                    j as i32
+                }
+                None => {
+                    palette.push(block);
+                    palette.len() as i32 - 1
+                }
+            };
+        }
+
+        for i in self.block_indices.iter_mut().flatten() {
+            *i = usize::try_from(*i)
+                .ok()
+                .and_then(|i| remap.get(i).copied())
+                .unwrap_or(-1);
+        }
+        report.entries_after = palette.len();
+        report.bytes_saved = before - palette_len(&palette);
+        self.block_palette = palette;
+        report
+    }
+}
@@ -0,0 +1,105 @@
+//! 将一个结构粘贴到另一个结构中
+use super::{position_of, transform::set_int, McStructure};
+use crate::Value;
+
+///粘贴时源结构中的空气与structure_void是否覆盖目标结构
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    ///所有位置都覆盖目标, structure_void会清空目标中的方块
+    Replace,
+    ///structure_void不覆盖目标, 与结构方块的加载行为一致
+    IgnoreVoid,
+    ///空气与structure_void都不覆盖目标
+    IgnoreAir,
+}
+
+impl McStructure {
+    ///将`src`粘贴到自身的相对坐标`offset`处, 超出范围的部分会被忽略
+    ///
+    ///调色板会被合并, `block_position_data`与实体的世界坐标会被平移到新的位置
+    pub fn paste(&mut self, src: &McStructure, offset: [i32; 3], mode: PasteMode) {
+        //只合并实际被粘贴的方块
+        let mut palette = vec![None; src.block_palette.len()];
+        let mut remap = |dest: &mut McStructure, i: i32| match usize::try_from(i) {
+            Ok(i) if i < palette.len() => {
+                *palette[i].get_or_insert_with(|| dest.palette_index(&src.block_palette[i]))
+            }
+            _ => -1,
+        };
+        let skip = |i: i32| match mode {
+            PasteMode::Replace => false,
+            PasteMode::IgnoreVoid => i < 0,
+            PasteMode::IgnoreAir => {
+                i < 0
+                    || src
+                        .block_palette
+                        .get(i as usize)
+                        .is_some_and(|b| b.is_air())
+            }
+        };
+
+        for (index, &block) in src.block_indices[0].iter().enumerate() {
+            let pos = position_of(src.size, index);
+            let [x, y, z] = [0, 1, 2].map(|i| pos[i] + offset[i]);
+            let Some(target) = self.index(x, y, z) else {
+                continue;
+            };
+            if skip(block) {
+                continue;
+            }
+            let primary = remap(self, block);
+            let secondary = remap(self, src.block_indices[1][index]);
+            self.set_index(target, primary);
+            self.block_indices[1][target] = secondary;
+            self.block_position_data.remove(&(target as i32));
+            if let Some(data) = src.block_position_data.get(&(index as i32)) {
+                let mut data = data.clone();
+                if let Some(Value::Compound(entity)) = data.get_mut("block_entity_data") {
+                    for (i, key) in ["x", "y", "z"].into_iter().enumerate() {
+                        if let Some(v) = entity.get_mut(key) {
+                            set_int(v, (self.structure_world_origin[i] + [x, y, z][i]) as i64);
+                        }
+                    }
+                    //大箱子的另一半按照相同的距离平移
+                    for (i, key) in [(0, "pairx"), (2, "pairz")] {
+                        let delta = self.structure_world_origin[i] + offset[i]
+                            - src.structure_world_origin[i];
+                        if let Some(v) = entity.get_mut(key) {
+                            if let Some(n) = v.to_i64() {
+                                set_int(v, n + delta as i64);
+                            }
+                        }
+                    }
+                }
+                self.block_position_data.insert(target as i32, data);
+            }
+        }
+
+        let shift = [0, 1, 2].map(|i| {
+            (self.structure_world_origin[i] + offset[i] - src.structure_world_origin[i]) as f64
+        });
+        let min = self.structure_world_origin.map(f64::from);
+        let max = [0, 1, 2].map(|i| min[i] + self.size[i] as f64);
+        for entity in &src.entities {
+            let mut entity = entity.clone();
+            if let Some(Value::List(pos)) = entity.get_mut("Pos") {
+                let mut inside = true;
+                for (i, p) in pos.iter_mut().enumerate().take(3) {
+                    let v = match p.to_f64() {
+                        Some(v) => v + shift[i],
+                        None => continue,
+                    };
+                    inside &= v >= min[i] && v < max[i];
+                    *p = match p {
+                        Value::Float(_) => Value::Float(v as f32),
+                        _ => Value::Double(v),
+                    };
+                }
+                if !inside {
+                    continue;
+                }
+            }
+            self.entities.push(entity);
+        }
+    }
+}
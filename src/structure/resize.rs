@@ -0,0 +1,110 @@
+//! 结构的裁剪, 扩展与自动修剪
+//!
+//! 方块实体与实体保存的是世界坐标, 改变结构大小时只需要移动
+//! `structure_world_origin`与`block_position_data`的键, 超出新范围的内容会被移除
+use super::{index_of, position_of, BlockState, McStructure};
+use crate::{Error, IResult, Map, Value};
+
+///扩展结构时用于填充的方块
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    Air,
+    StructureVoid,
+}
+
+impl McStructure {
+    ///裁剪到以相对坐标`min`为起点, 大小为`size`的区域
+    pub fn crop(&mut self, min: [i32; 3], size: [i32; 3]) -> IResult<()> {
+        for i in 0..3 {
+            if min[i] < 0 || size[i] < 0 || min[i] + size[i] > self.size[i] {
+                return Err(Error::OutOfBounds([0, 1, 2].map(|i| min[i] + size[i])));
+            }
+        }
+        self.reshape(min, size, -1);
+        Ok(())
+    }
+
+    ///在结构的负方向扩展`before`, 正方向扩展`after`
+    pub fn expand(&mut self, before: [i32; 3], after: [i32; 3], padding: Padding) {
+        let padded = before.iter().chain(&after).any(|&v| v > 0);
+        let fill = match padding {
+            Padding::Air if padded => self.palette_index(&BlockState::new("minecraft:air")),
+            _ => -1,
+        };
+        let size = [0, 1, 2].map(|i| self.size[i] + before[i].max(0) + after[i].max(0));
+        self.reshape(before.map(|v| -v.max(0)), size, fill);
+    }
+
+    ///主层中不是structure_void的方块的包围盒, 返回起点与大小,
+    ///`ignore_air`为`true`时空气也被视为空
+    pub fn bounding_box(&self, ignore_air: bool) -> Option<([i32; 3], [i32; 3])> {
+        let air = self
+            .block_palette
+            .iter()
+            .map(|b| ignore_air && b.is_air())
+            .collect::<Vec<_>>();
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for (index, &block) in self.block_indices[0].iter().enumerate() {
+            if block < 0 || air.get(block as usize).copied().unwrap_or(false) {
+                continue;
+            }
+            let pos = position_of(self.size, index);
+            for i in 0..3 {
+                min[i] = min[i].min(pos[i]);
+                max[i] = max[i].max(pos[i]);
+            }
+        }
+        (min[0] <= max[0]).then(|| (min, [0, 1, 2].map(|i| max[i] - min[i] + 1)))
+    }
+
+    ///裁剪到[`Self::bounding_box`], 结构中没有方块时大小变为0
+    pub fn trim(&mut self, ignore_air: bool) {
+        let (min, size) = self.bounding_box(ignore_air).unwrap_or(([0; 3], [0; 3]));
+        self.reshape(min, size, -1);
+    }
+
+    ///以相对坐标`offset`为新的原点, 改变大小为`size`, 新增的位置使用调色板下标`fill`
+    fn reshape(&mut self, offset: [i32; 3], size: [i32; 3], fill: i32) {
+        let old_size = self.size;
+        let volume = size.iter().map(|&v| v.max(0) as usize).product();
+        let inside = |pos: [i32; 3]| (0..3).all(|i| (0..size[i]).contains(&pos[i]));
+        let moved = |index: usize| {
+            let pos = position_of(old_size, index);
+            let pos = [0, 1, 2].map(|i| pos[i] - offset[i]);
+            inside(pos).then(|| index_of(size, pos))
+        };
+
+        let mut layers = [vec![fill; volume], vec![-1; volume]];
+        for (layer, indices) in self.block_indices.iter().enumerate() {
+            for (index, &block) in indices.iter().enumerate() {
+                if let Some(new) = moved(index) {
+                    layers[layer][new] = block;
+                }
+            }
+        }
+
+        let mut data = Map::new();
+        for (&index, value) in &self.block_position_data {
+            if let Some(new) = moved(index as usize) {
+                data.insert(new as i32, value.clone());
+            }
+        }
+
+        let origin = [0, 1, 2].map(|i| self.structure_world_origin[i] + offset[i]);
+        self.entities.retain(|entity| match entity.get("Pos") {
+            Some(Value::List(pos)) if pos.len() == 3 => (0..3).all(|i| {
+                pos[i].to_f64().is_none_or(|p| {
+                    let p = p - origin[i] as f64;
+                    p >= 0.0 && p < size[i] as f64
+                })
+            }),
+            _ => true,
+        });
+
+        self.size = size;
+        self.structure_world_origin = origin;
+        self.block_indices = layers;
+        self.block_position_data = data;
+    }
+}
@@ -0,0 +1,109 @@
+//! 方块状态的值类型
+//!
+//! 游戏会忽略类型错误的状态, 例如`open_bit`必须为`TAG_Byte`, `facing_direction`必须为`TAG_Int`,
+//! 各个键的类型见`block_states.txt`
+use super::{BlockState, McStructure};
+use crate::{compound::sort_keys, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+///方块状态值的标签类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateType {
+    Byte,
+    Int,
+    String,
+}
+
+impl StateType {
+    ///已知的键对应的类型, 未知的键返回`None`
+    pub fn of(key: &str) -> Option<StateType> {
+        if key.ends_with("_bit") {
+            return Some(StateType::Byte);
+        }
+        schema().get(key).copied()
+    }
+
+    pub fn tag_name(self) -> &'static str {
+        match self {
+            StateType::Byte => "TAG_Byte",
+            StateType::Int => "TAG_Int",
+            StateType::String => "TAG_String",
+        }
+    }
+
+    ///将`value`转换为这一类型, 无法转换时返回`None`
+    pub fn coerce(self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (StateType::Byte, Value::Byte(_))
+            | (StateType::Int, Value::Int(_))
+            | (StateType::String, Value::String(_)) => Some(value.clone()),
+            (StateType::Byte, Value::String(s)) => match s.as_str() {
+                "true" => Some(Value::Byte(1)),
+                "false" => Some(Value::Byte(0)),
+                s => s.parse().ok().map(Value::Byte),
+            },
+            (StateType::Byte, v) => v.to_i64()?.try_into().ok().map(Value::Byte),
+            (StateType::Int, Value::String(s)) => s.parse().ok().map(Value::Int),
+            (StateType::Int, v) => v.to_i64()?.try_into().ok().map(Value::Int),
+            (StateType::String, v) => v.to_i64().map(|v| Value::String(v.to_string())),
+        }
+    }
+}
+
+fn schema() -> &'static HashMap<&'static str, StateType> {
+    static SCHEMA: OnceLock<HashMap<&'static str, StateType>> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let mut schema = HashMap::new();
+        for line in include_str!("block_states.txt").lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let ty = match parts.next() {
+                Some("byte") => StateType::Byte,
+                Some("int") => StateType::Int,
+                Some("string") => StateType::String,
+                _ => panic!("block_states.txt: {line}"),
+            };
+            schema.extend(parts.map(|key| (key, ty)));
+        }
+        schema
+    })
+}
+
+impl BlockState {
+    ///按照状态的类型表转换值的类型, 返回被修改的状态数, 未知的键与无法转换的值保持不变
+    pub fn coerce_states(&mut self) -> usize {
+        let mut changed = 0;
+        for (key, value) in self.states.iter_mut() {
+            let Some(new) = StateType::of(key).and_then(|ty| ty.coerce(value)) else {
+                continue;
+            };
+            if new != *value {
+                *value = new;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    ///按字节序排列状态, 与游戏保存的顺序一致
+    pub fn sort_states(&mut self) {
+        sort_keys(&mut self.states);
+    }
+}
+
+impl McStructure {
+    ///转换调色板中所有状态的类型并排列状态, 返回被修改类型的状态数
+    pub fn normalize_states(&mut self) -> usize {
+        self.block_palette
+            .iter_mut()
+            .map(|block| {
+                block.sort_states();
+                block.coerce_states()
+            })
+            .sum()
+    }
+}
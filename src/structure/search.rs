@@ -0,0 +1,119 @@
+//! 按名称与部分方块状态查找和替换方块
+use super::{block::parse_block, position_of, BlockState, McStructure};
+use crate::{Error, Map, Value};
+use std::str::FromStr;
+
+///方块的匹配条件, 只比较其中列出的状态
+///
+///```
+///use nbtrock::structure::{BlockPattern, BlockState};
+///let pattern: BlockPattern = "observer[facing_direction=3]".parse().unwrap();
+///let mut block = BlockState::new("minecraft:observer");
+///block.states.insert("facing_direction".into(), 3.into());
+///block.states.insert("powered_bit".into(), false.into());
+///assert!(pattern.matches(&block));
+///```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPattern {
+    pub name: String,
+    pub states: Map<String, Value>,
+}
+
+impl FromStr for BlockPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, states) = parse_block(s)?;
+        Ok(BlockPattern { name, states })
+    }
+}
+
+///整数状态不区分`TAG_Byte`与`TAG_Int`
+fn same_state(a: &Value, b: &Value) -> bool {
+    match (a.to_i64(), b.to_i64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+impl BlockPattern {
+    pub fn matches(&self, block: &BlockState) -> bool {
+        self.name == block.name
+            && self
+                .states
+                .iter()
+                .all(|(k, v)| block.states.get(k).is_some_and(|b| same_state(v, b)))
+    }
+}
+
+impl McStructure {
+    ///主层与次层中匹配`pattern`的方块的相对坐标
+    pub fn find(&self, pattern: &BlockPattern) -> Vec<[i32; 3]> {
+        let matched = self.matched(pattern);
+        let mut found = Vec::new();
+        for indices in &self.block_indices {
+            for (index, &i) in indices.iter().enumerate() {
+                if usize::try_from(i).is_ok_and(|i| matched.get(i) == Some(&true)) {
+                    found.push(position_of(self.size, index));
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    ///将匹配`pattern`的方块替换为`with`, 返回被替换的方块数
+    ///
+    ///替换直接修改调色板中的项, 不会逐个方块地修改`block_indices`.
+    ///`keep_states`为`true`时, `with`中已有的状态若在原方块中也存在, 则使用原方块的值,
+    ///例如将任意朝向的observer替换为同朝向的dispenser.
+    ///方块名称改变时, 对应位置的`block_position_data`会被移除
+    pub fn replace(
+        &mut self,
+        pattern: &BlockPattern,
+        with: &BlockState,
+        keep_states: bool,
+    ) -> usize {
+        let matched = self.matched(pattern);
+        let mut renamed = vec![false; matched.len()];
+        for (i, block) in self.block_palette.iter_mut().enumerate() {
+            if !matched[i] {
+                continue;
+            }
+            let mut new = with.clone();
+            if keep_states {
+                for (k, v) in new.states.iter_mut() {
+                    if let Some(old) = block.states.get(k) {
+                        *v = old.clone();
+                    }
+                }
+            }
+            renamed[i] = new.name != block.name;
+            *block = new;
+        }
+
+        let mut count = 0;
+        for (layer, indices) in self.block_indices.iter().enumerate() {
+            for (index, &i) in indices.iter().enumerate() {
+                let Ok(i) = usize::try_from(i) else {
+                    continue;
+                };
+                if matched.get(i) == Some(&true) {
+                    count += 1;
+                    if layer == 0 && renamed[i] {
+                        self.block_position_data.remove(&(index as i32));
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    fn matched(&self, pattern: &BlockPattern) -> Vec<bool> {
+        self.block_palette
+            .iter()
+            .map(|b| pattern.matches(b))
+            .collect()
+    }
+}
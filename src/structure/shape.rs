@@ -0,0 +1,157 @@
+//! 几何形状的填充
+//!
+//! 所有坐标都是相对坐标, 两个角的坐标都包含在区域内, 与`/fill`一致.
+//! 超出结构范围的部分会被忽略, 返回值是实际设置的方块数
+use super::{BlockState, McStructure};
+
+impl McStructure {
+    ///将区域内满足`pred`的位置设置为调色板下标`palette`
+    pub(crate) fn fill_where(
+        &mut self,
+        from: [i32; 3],
+        to: [i32; 3],
+        palette: i32,
+        pred: impl Fn([i32; 3]) -> bool,
+    ) -> usize {
+        let min = [0, 1, 2].map(|i| from[i].min(to[i]).max(0));
+        let max = [0, 1, 2].map(|i| from[i].max(to[i]).min(self.size[i] - 1));
+        let mut count = 0;
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    if pred([x, y, z]) {
+                        let index = self.index(x, y, z).unwrap();
+                        self.set_index(index, palette);
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    ///填充整个区域
+    pub fn fill(&mut self, from: [i32; 3], to: [i32; 3], block: &BlockState) -> usize {
+        let palette = self.palette_index(block);
+        self.fill_where(from, to, palette, |_| true)
+    }
+
+    ///只填充区域的外壳, 内部不变, 与`/fill ... outline`一致
+    pub fn outline(&mut self, from: [i32; 3], to: [i32; 3], block: &BlockState) -> usize {
+        let palette = self.palette_index(block);
+        self.fill_where(from, to, palette, |pos| on_face(from, to, pos, [0, 1, 2]))
+    }
+
+    ///填充区域的外壳, 内部设置为空气, 与`/fill ... hollow`一致
+    pub fn hollow(&mut self, from: [i32; 3], to: [i32; 3], block: &BlockState) -> usize {
+        //内部与结构没有交集时不加入空气
+        let lo = [0, 1, 2].map(|i| from[i].min(to[i]).saturating_add(1).max(0));
+        let hi = [0, 1, 2].map(|i| from[i].max(to[i]).saturating_sub(1).min(self.size[i] - 1));
+        let mut inner = 0;
+        if (0..3).all(|i| lo[i] <= hi[i]) {
+            let air = self.palette_index(&BlockState::new("minecraft:air"));
+            inner = self.fill_where(lo, hi, air, |_| true);
+        }
+        inner + self.outline(from, to, block)
+    }
+
+    ///只填充区域四周的竖直墙面, 不包括地面与顶面
+    pub fn walls(&mut self, from: [i32; 3], to: [i32; 3], block: &BlockState) -> usize {
+        let palette = self.palette_index(block);
+        self.fill_where(from, to, palette, |pos| on_face(from, to, pos, [0, 2]))
+    }
+
+    ///以`center`为球心, 填充到球心距离不超过`radius`的方块, `hollow`为`true`时只填充表面
+    pub fn sphere(
+        &mut self,
+        center: [i32; 3],
+        radius: f64,
+        block: &BlockState,
+        hollow: bool,
+    ) -> usize {
+        //半径超过i32范围时饱和, 区域会被裁剪到结构范围内
+        let r = radius.max(0.0).floor() as i32;
+        let inside = |pos: [i32; 3]| {
+            let d = [0, 1, 2].map(|i| pos[i] as f64 - center[i] as f64);
+            d[0] * d[0] + d[1] * d[1] + d[2] * d[2] <= radius * radius
+        };
+        let palette = self.palette_index(block);
+        self.fill_where(
+            center.map(|v| v.saturating_sub(r)),
+            center.map(|v| v.saturating_add(r)),
+            palette,
+            |pos| inside(pos) && (!hollow || neighbours(pos, [0, 1, 2]).any(|n| !inside(n))),
+        )
+    }
+
+    ///以`base`为底面圆心, 沿y轴向上填充高度为`height`的圆柱, `hollow`为`true`时只填充侧面
+    pub fn cylinder(
+        &mut self,
+        base: [i32; 3],
+        radius: f64,
+        height: i32,
+        block: &BlockState,
+        hollow: bool,
+    ) -> usize {
+        if height <= 0 {
+            return 0;
+        }
+        let r = radius.max(0.0).floor() as i32;
+        let inside = |pos: [i32; 3]| {
+            let d = [0, 2].map(|i| pos[i] as f64 - base[i] as f64);
+            d[0] * d[0] + d[1] * d[1] <= radius * radius
+        };
+        let palette = self.palette_index(block);
+        self.fill_where(
+            [
+                base[0].saturating_sub(r),
+                base[1],
+                base[2].saturating_sub(r),
+            ],
+            [
+                base[0].saturating_add(r),
+                base[1].saturating_add(height - 1),
+                base[2].saturating_add(r),
+            ],
+            palette,
+            |pos| inside(pos) && (!hollow || neighbours(pos, [0, 2]).any(|n| !inside(n))),
+        )
+    }
+
+    ///从`from`到`to`画一条直线
+    pub fn line(&mut self, from: [i32; 3], to: [i32; 3], block: &BlockState) -> usize {
+        let palette = self.palette_index(block);
+        let d = [0, 1, 2].map(|i| to[i] - from[i]);
+        let steps = d.iter().map(|v| v.abs()).max().unwrap();
+        let mut count = 0;
+        for step in 0..=steps {
+            let t = match steps {
+                0 => 0.0,
+                _ => step as f64 / steps as f64,
+            };
+            let [x, y, z] = [0, 1, 2].map(|i| from[i] + (d[i] as f64 * t).round() as i32);
+            if let Some(index) = self.index(x, y, z) {
+                self.set_index(index, palette);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+///`pos`是否位于区域在`axes`方向上的边界面
+fn on_face<const N: usize>(from: [i32; 3], to: [i32; 3], pos: [i32; 3], axes: [usize; N]) -> bool {
+    axes.iter()
+        .any(|&i| pos[i] == from[i].min(to[i]) || pos[i] == from[i].max(to[i]))
+}
+
+///`pos`在`axes`方向上相邻的位置
+fn neighbours<const N: usize>(pos: [i32; 3], axes: [usize; N]) -> impl Iterator<Item = [i32; 3]> {
+    axes.into_iter().flat_map(move |i| {
+        [-1, 1].map(|d| {
+            let mut n = pos;
+            n[i] += d;
+            n
+        })
+    })
+}
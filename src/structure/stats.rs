@@ -0,0 +1,170 @@
+//! 材料清单与结构统计
+use super::McStructure;
+use crate::{Map, Value};
+use std::fmt::Write;
+
+///[`McStructure::materials`]的结果, 各项按数量从多到少排列, 数量相同时按名称排列
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Materials {
+    pub volume: usize,
+    ///主层中既不是空气也不是structure_void的方块数
+    pub non_air: usize,
+    ///主层与次层中的方块, 不包括空气
+    pub blocks: Vec<(String, u64)>,
+    ///容器`Items`中的物品, 按`Count`累加
+    pub items: Vec<(String, u64)>,
+    ///实体, 按`identifier`统计
+    pub entities: Vec<(String, u64)>,
+}
+
+fn sorted(map: Map<String, u64>) -> Vec<(String, u64)> {
+    let mut v: Vec<_> = map.into_iter().collect();
+    v.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    v
+}
+
+fn add(map: &mut Map<String, u64>, key: String, count: u64) {
+    *map.entry(key).or_insert(0) += count;
+}
+
+impl McStructure {
+    ///统计方块, 容器中的物品与实体, `with_states`为`true`时不同状态的方块分别统计
+    pub fn materials(&self, with_states: bool) -> Materials {
+        let names = self
+            .block_palette
+            .iter()
+            .map(|b| {
+                (!b.is_air()).then(|| match with_states {
+                    true => b.to_string(),
+                    false => b.name.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut counts = vec![0u64; names.len()];
+        let mut non_air = 0;
+        for (layer, indices) in self.block_indices.iter().enumerate() {
+            for &i in indices {
+                let Some(name) = usize::try_from(i).ok().and_then(|i| names.get(i)) else {
+                    continue;
+                };
+                if name.is_some() {
+                    counts[i as usize] += 1;
+                    non_air += (layer == 0) as usize;
+                }
+            }
+        }
+        let mut blocks = Map::new();
+        for (name, count) in names.into_iter().zip(counts) {
+            if let Some(name) = name.filter(|_| count > 0) {
+                add(&mut blocks, name, count);
+            }
+        }
+
+        let mut items = Map::new();
+        for data in self.block_position_data.values() {
+            let list = data
+                .get("block_entity_data")
+                .and_then(|v| v.get("Items"))
+                .and_then(Value::as_list);
+            for item in list.into_iter().flatten() {
+                let (Some(name), Some(count)) = (
+                    item.get("Name").and_then(Value::as_str),
+                    item.get("Count").and_then(Value::to_i64),
+                ) else {
+                    continue;
+                };
+                if !name.is_empty() && count > 0 {
+                    add(&mut items, name.to_owned(), count as u64);
+                }
+            }
+        }
+
+        let mut entities = Map::new();
+        for entity in &self.entities {
+            if let Some(Value::String(id)) = entity.get("identifier") {
+                add(&mut entities, id.clone(), 1);
+            }
+        }
+
+        Materials {
+            volume: self.volume(),
+            non_air,
+            blocks: sorted(blocks),
+            items: sorted(items),
+            entities: sorted(entities),
+        }
+    }
+}
+
+impl Materials {
+    ///非空气方块占结构体积的比例, 体积为0时返回0
+    pub fn non_air_ratio(&self) -> f64 {
+        match self.volume {
+            0 => 0.0,
+            v => self.non_air as f64 / v as f64,
+        }
+    }
+
+    ///输出为CSV, 列为`category,name,count`
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("category,name,count\n");
+        for (category, list) in [
+            ("block", &self.blocks),
+            ("item", &self.items),
+            ("entity", &self.entities),
+        ] {
+            for (name, count) in list {
+                let name = match name.contains([',', '"', '\n']) {
+                    true => format!("\"{}\"", name.replace('"', "\"\"")),
+                    false => name.clone(),
+                };
+                writeln!(out, "{category},{name},{count}").unwrap();
+            }
+        }
+        out
+    }
+
+    ///输出为JSON对象
+    pub fn to_json(&self) -> String {
+        let mut out = format!(
+            "{{\"volume\":{},\"non_air\":{},\"non_air_ratio\":{}",
+            self.volume,
+            self.non_air,
+            self.non_air_ratio()
+        );
+        for (category, list) in [
+            ("blocks", &self.blocks),
+            ("items", &self.items),
+            ("entities", &self.entities),
+        ] {
+            write!(out, ",\"{category}\":{{").unwrap();
+            for (index, (name, count)) in list.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write!(out, "{}:{count}", json_string(name)).unwrap();
+            }
+            out.push('}');
+        }
+        out.push('}');
+        out
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
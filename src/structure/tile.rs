@@ -0,0 +1,214 @@
+//! 将大型结构拆分为结构方块能够保存的分块, 以及将分块拼接为一个结构
+use super::{function::relative_coords, index_of, stats::json_string, McStructure, PasteMode};
+use crate::{Map, Value};
+use std::fmt::Write;
+
+///结构方块能够保存的最大尺寸
+pub const MAX_TILE_SIZE: [i32; 3] = [64, 384, 64];
+
+///[`McStructure::split`]产生的分块
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    ///在分块网格中的位置
+    pub grid: [i32; 3],
+    ///相对于原结构的坐标
+    pub offset: [i32; 3],
+    pub structure: McStructure,
+}
+
+impl Tile {
+    ///分块的结构名称, 例如`prefix_0_1_0`
+    pub fn name(&self, prefix: &str) -> String {
+        let [x, y, z] = self.grid;
+        format!("{prefix}_{x}_{y}_{z}")
+    }
+}
+
+///拆分后的所有分块
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileSet {
+    ///原结构的大小
+    pub size: [i32; 3],
+    ///原结构的`structure_world_origin`
+    pub origin: [i32; 3],
+    pub tiles: Vec<Tile>,
+}
+
+impl McStructure {
+    ///拆分为大小不超过`max`的分块, 方块实体与实体会被分配到所在的分块中,
+    ///没有有效坐标的实体放在第一个分块中, 每个分块的调色板只保留用到的方块
+    pub fn split(&self, max: [i32; 3]) -> TileSet {
+        let max = max.map(|v| v.max(1));
+        let count = [0, 1, 2].map(|i| (self.size[i].max(0) + max[i] - 1) / max[i]);
+        let mut contents = vec![Contents::default(); count.iter().map(|&v| v as usize).product()];
+        //只遍历一次方块实体与实体, 按原有顺序分配到各个分块
+        for (&key, data) in &self.block_position_data {
+            if !(0..self.volume() as i32).contains(&key) {
+                continue;
+            }
+            let pos = self.position(key as usize);
+            let grid = [0, 1, 2].map(|i| pos[i] / max[i]);
+            let local = [0, 1, 2].map(|i| pos[i] - grid[i] * max[i]);
+            let size = [0, 1, 2].map(|i| max[i].min(self.size[i] - grid[i] * max[i]));
+            contents[index_of(count, grid)]
+                .data
+                .insert(index_of(size, local) as i32, data.clone());
+        }
+        for entity in &self.entities {
+            match entity_grid(entity, self.structure_world_origin, max) {
+                Some(grid) if (0..3).all(|i| (0..count[i]).contains(&grid[i])) => contents
+                    [index_of(count, grid)]
+                .entities
+                .push(entity.clone()),
+                Some(_) => {}
+                //没有有效坐标的实体放在第一个分块中, 拼接时不会重复
+                None => {
+                    if let Some(first) = contents.first_mut() {
+                        first.entities.push(entity.clone());
+                    }
+                }
+            }
+        }
+
+        let mut tiles = Vec::new();
+        for x in 0..count[0] {
+            for y in 0..count[1] {
+                for z in 0..count[2] {
+                    let grid = [x, y, z];
+                    let offset = [0, 1, 2].map(|i| grid[i] * max[i]);
+                    let size = [0, 1, 2].map(|i| max[i].min(self.size[i] - offset[i]));
+                    let contents = std::mem::take(&mut contents[index_of(count, grid)]);
+                    let mut structure = self.region(offset, size, contents);
+                    structure.compact_palette();
+                    tiles.push(Tile {
+                        grid,
+                        offset,
+                        structure,
+                    });
+                }
+            }
+        }
+        TileSet {
+            size: self.size,
+            origin: self.structure_world_origin,
+            tiles,
+        }
+    }
+
+    ///以相对坐标`min`为起点, 大小为`size`的区域, 只复制区域内的方块
+    fn region(&self, min: [i32; 3], size: [i32; 3], contents: Contents) -> McStructure {
+        let mut region = McStructure::new(size);
+        region.name = self.name.clone();
+        region.format_version = self.format_version;
+        region.structure_world_origin = [0, 1, 2].map(|i| self.structure_world_origin[i] + min[i]);
+        region.block_palette = self.block_palette.clone();
+        region.unknown = self.unknown.clone();
+        region.block_position_data = contents.data;
+        region.entities = contents.entities;
+        for x in 0..size[0] {
+            for y in 0..size[1] {
+                for z in 0..size[2] {
+                    let from = index_of(self.size, [min[0] + x, min[1] + y, min[2] + z]);
+                    let to = index_of(size, [x, y, z]);
+                    for layer in 0..2 {
+                        region.block_indices[layer][to] = self.block_indices[layer][from];
+                    }
+                }
+            }
+        }
+        region
+    }
+
+    ///将分块按各自的`offset`拼接为一个结构, [`McStructure::split`]的逆运算
+    ///
+    ///`name`, `format_version`与未被识别的键取自第一个分块
+    pub fn join(tiles: &[Tile]) -> McStructure {
+        let Some(first) = tiles.first() else {
+            return McStructure::new([0; 3]);
+        };
+        let size = [0, 1, 2].map(|i| {
+            tiles
+                .iter()
+                .map(|t| t.offset[i] + t.structure.size[i])
+                .max()
+                .unwrap_or(0)
+        });
+        let mut structure = McStructure::new(size);
+        structure.name = first.structure.name.clone();
+        structure.format_version = first.structure.format_version;
+        structure.unknown = first.structure.unknown.clone();
+        structure.structure_world_origin =
+            [0, 1, 2].map(|i| first.structure.structure_world_origin[i] - first.offset[i]);
+        for tile in tiles {
+            structure.paste(&tile.structure, tile.offset, PasteMode::Replace);
+        }
+        structure
+    }
+}
+
+impl TileSet {
+    pub fn join(&self) -> McStructure {
+        McStructure::join(&self.tiles)
+    }
+
+    ///按顺序加载所有分块的`/structure load`命令, `origin`为`None`时使用相对坐标
+    pub fn load_commands(&self, prefix: &str, origin: Option<[i32; 3]>) -> Vec<String> {
+        self.tiles
+            .iter()
+            .map(|tile| {
+                let pos = match origin {
+                    Some(origin) => [0, 1, 2]
+                        .map(|i| (origin[i] + tile.offset[i]).to_string())
+                        .join(" "),
+                    None => relative_coords(tile.offset),
+                };
+                format!("structure load {} {pos}", tile.name(prefix))
+            })
+            .collect()
+    }
+
+    ///JSON格式的清单, 记录原结构的大小与每个分块的名称, 偏移和大小
+    pub fn manifest(&self, prefix: &str) -> String {
+        let array = |v: [i32; 3]| format!("[{},{},{}]", v[0], v[1], v[2]);
+        let mut out = format!(
+            "{{\"size\":{},\"origin\":{},\"tiles\":[",
+            array(self.size),
+            array(self.origin)
+        );
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "{{\"name\":{},\"grid\":{},\"offset\":{},\"size\":{}}}",
+                json_string(&tile.name(prefix)),
+                array(tile.grid),
+                array(tile.offset),
+                array(tile.structure.size)
+            )
+            .unwrap();
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+///分配到一个分块中的`block_position_data`(以分块中的下标为键)与实体
+#[derive(Clone, Default)]
+struct Contents {
+    data: Map<i32, Map<String, Value>>,
+    entities: Vec<Map<String, Value>>,
+}
+
+///实体所在的分块, `Pos`无效时返回`None`
+fn entity_grid(entity: &Map<String, Value>, origin: [i32; 3], max: [i32; 3]) -> Option<[i32; 3]> {
+    let Some(Value::List(pos)) = entity.get("Pos") else {
+        return None;
+    };
+    let [x, y, z] = pos.as_slice() else {
+        return None;
+    };
+    let pos = [x.to_f64()?, y.to_f64()?, z.to_f64()?];
+    Some([0, 1, 2].map(|i| ((pos[i] - origin[i] as f64) / max[i] as f64).floor() as i32))
+}
@@ -0,0 +1,398 @@
+//! 结构的旋转与镜像
+//!
+//! 除了方块与实体的位置外, 方块的朝向状态(`facing_direction`, `direction`,
+//! `weirdo_direction`, `torch_facing_direction`, `pillar_axis`, `wall_connection_type_*`等)
+//! 以及实体的`Rotation`也会被相应地改写
+use super::{index_of, position_of, McStructure};
+use crate::{Map, Value};
+
+///绕Y轴的旋转, 方向为从上往下看时的顺时针
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+///镜像所沿的轴
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    ///x坐标取反, 东西互换
+    X,
+    ///z坐标取反, 南北互换
+    Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Down,
+    Up,
+    North,
+    South,
+    West,
+    East,
+}
+
+use Dir::*;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Transform {
+    Rotate(Rotation),
+    Mirror(Mirror),
+}
+
+impl Transform {
+    fn quarter_turns(self) -> usize {
+        match self {
+            Transform::Rotate(Rotation::Clockwise90) => 1,
+            Transform::Rotate(Rotation::Clockwise180) => 2,
+            Transform::Rotate(Rotation::Clockwise270) => 3,
+            Transform::Mirror(_) => 0,
+        }
+    }
+
+    fn swaps_axes(self) -> bool {
+        self.quarter_turns() % 2 == 1
+    }
+
+    fn dir(self, d: Dir) -> Dir {
+        match self {
+            Transform::Rotate(_) => (0..self.quarter_turns()).fold(d, |d, _| match d {
+                North => East,
+                East => South,
+                South => West,
+                West => North,
+                d => d,
+            }),
+            Transform::Mirror(Mirror::X) => match d {
+                East => West,
+                West => East,
+                d => d,
+            },
+            Transform::Mirror(Mirror::Z) => match d {
+                North => South,
+                South => North,
+                d => d,
+            },
+        }
+    }
+
+    pub(crate) fn size(self, [x, y, z]: [i32; 3]) -> [i32; 3] {
+        if self.swaps_axes() {
+            [z, y, x]
+        } else {
+            [x, y, z]
+        }
+    }
+
+    ///变换`size`范围内的坐标, `edge`为1时变换方块坐标, 为0时变换实体的连续坐标
+    fn point(self, size: [f64; 3], [x, y, z]: [f64; 3], edge: f64) -> [f64; 3] {
+        let [sx, _, sz] = size;
+        match self {
+            Transform::Rotate(Rotation::Clockwise90) => [sz - edge - z, y, x],
+            Transform::Rotate(Rotation::Clockwise180) => [sx - edge - x, y, sz - edge - z],
+            Transform::Rotate(Rotation::Clockwise270) => [z, y, sx - edge - x],
+            Transform::Mirror(Mirror::X) => [sx - edge - x, y, z],
+            Transform::Mirror(Mirror::Z) => [x, y, sz - edge - z],
+        }
+    }
+
+    pub(crate) fn block(self, size: [i32; 3], [x, y, z]: [i32; 3]) -> [i32; 3] {
+        let size = size.map(f64::from);
+        self.point(size, [x, y, z].map(f64::from), 1.0)
+            .map(|v| v as i32)
+    }
+
+    fn vector(self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        self.point([0.0; 3], [x, y, z], 0.0)
+    }
+
+    fn yaw(self, yaw: f32) -> f32 {
+        let yaw = match self {
+            Transform::Rotate(_) => yaw + 90.0 * self.quarter_turns() as f32,
+            Transform::Mirror(Mirror::X) => -yaw,
+            Transform::Mirror(Mirror::Z) => 180.0 - yaw,
+        };
+        (yaw + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+impl McStructure {
+    pub fn rotate(&mut self, rotation: Rotation) {
+        self.transform(Transform::Rotate(rotation))
+    }
+
+    pub fn mirror(&mut self, mirror: Mirror) {
+        self.transform(Transform::Mirror(mirror))
+    }
+
+    fn transform(&mut self, t: Transform) {
+        let size = self.size;
+        let new_size = t.size(size);
+        let map = |index: usize| index_of(new_size, t.block(size, position_of(size, index)));
+
+        let mut layers = [vec![-1; self.volume()], vec![-1; self.volume()]];
+        for (layer, indices) in self.block_indices.iter().enumerate() {
+            for (index, &block) in indices.iter().enumerate() {
+                layers[layer][map(index)] = block;
+            }
+        }
+
+        let origin = self.structure_world_origin;
+        let mut data = Map::new();
+        for (&index, value) in &self.block_position_data {
+            let mut value = value.clone();
+            let new_index = map(index as usize);
+            if let Some(Value::Compound(entity)) = value.get_mut("block_entity_data") {
+                let pos = position_of(new_size, new_index);
+                for (i, key) in ["x", "y", "z"].into_iter().enumerate() {
+                    if let Some(v) = entity.get_mut(key) {
+                        set_int(v, (origin[i] + pos[i]) as i64);
+                    }
+                }
+                //大箱子的另一半以世界坐标保存, 与所在方块的y相同
+                let pair = [entity.get("pairx"), entity.get("pairz")].map(|v| v.and_then(int_of));
+                if let [Some(px), Some(pz)] = pair {
+                    let y = position_of(size, index as usize)[1];
+                    let rel = [px as i32 - origin[0], y, pz as i32 - origin[2]];
+                    let moved = t.block(size, rel);
+                    for (i, key) in [(0, "pairx"), (2, "pairz")] {
+                        if let Some(v) = entity.get_mut(key) {
+                            set_int(v, (origin[i] + moved[i]) as i64);
+                        }
+                    }
+                }
+            }
+            data.insert(new_index as i32, value);
+        }
+
+        for block in &mut self.block_palette {
+            transform_states(&block.name, &mut block.states, t);
+        }
+
+        let size_f = size.map(f64::from);
+        let origin_f = origin.map(f64::from);
+        for entity in &mut self.entities {
+            if let Some(Value::List(pos)) = entity.get_mut("Pos") {
+                if let Some(p) = read_vec3(pos) {
+                    let rel = [0, 1, 2].map(|i| p[i] - origin_f[i]);
+                    let moved = t.point(size_f, rel, 0.0);
+                    write_vec3(pos, [0, 1, 2].map(|i| moved[i] + origin_f[i]));
+                }
+            }
+            if let Some(Value::List(motion)) = entity.get_mut("Motion") {
+                if let Some(m) = read_vec3(motion) {
+                    write_vec3(motion, t.vector(m));
+                }
+            }
+            if let Some(Value::List(rotation)) = entity.get_mut("Rotation") {
+                if let Some(Value::Float(yaw)) = rotation.first_mut() {
+                    *yaw = t.yaw(*yaw);
+                }
+            }
+        }
+
+        self.size = new_size;
+        self.block_indices = layers;
+        self.block_position_data = data;
+    }
+}
+
+fn read_vec3(list: &[Value]) -> Option<[f64; 3]> {
+    match list {
+        [x, y, z] => Some([x.to_f64()?, y.to_f64()?, z.to_f64()?]),
+        _ => None,
+    }
+}
+
+///按照原有的标签类型写回
+fn write_vec3(list: &mut [Value], v: [f64; 3]) {
+    for (value, v) in list.iter_mut().zip(v) {
+        *value = match value {
+            Value::Float(_) => Value::Float(v as f32),
+            _ => Value::Double(v),
+        }
+    }
+}
+
+const FACING: [Dir; 6] = [Down, Up, North, South, West, East];
+const DIRECTION: [Dir; 4] = [South, West, North, East];
+const DOOR_DIRECTION: [Dir; 4] = [East, South, West, North];
+const WEIRDO_DIRECTION: [Dir; 4] = [East, West, South, North];
+const CORAL_DIRECTION: [Dir; 4] = [West, East, North, South];
+const VINE_BITS: [Dir; 4] = [South, West, North, East];
+const MULTI_FACE_BITS: [Dir; 6] = [Down, Up, South, West, North, East];
+const NAMES: [(Dir, &str); 6] = [
+    (Down, "down"),
+    (Up, "up"),
+    (North, "north"),
+    (South, "south"),
+    (West, "west"),
+    (East, "east"),
+];
+///`rail_direction`的0~9: 是否为斜坡, 连接的两个方向
+const RAILS: [(bool, Dir, Dir); 10] = [
+    (false, North, South),
+    (false, East, West),
+    (true, East, East),
+    (true, West, West),
+    (true, North, North),
+    (true, South, South),
+    (false, South, East),
+    (false, South, West),
+    (false, North, West),
+    (false, North, East),
+];
+
+fn name_of(d: Dir) -> &'static str {
+    NAMES.iter().find(|(n, _)| *n == d).unwrap().1
+}
+
+fn dir_of(s: &str) -> Option<Dir> {
+    NAMES.iter().find(|(_, n)| *n == s).map(|(d, _)| *d)
+}
+
+fn int_of(v: &Value) -> Option<i64> {
+    v.to_i64()
+}
+
+///保持原有的整数标签类型
+pub(crate) fn set_int(v: &mut Value, n: i64) {
+    *v = match v {
+        Value::Byte(_) => Value::Byte(n as i8),
+        Value::Short(_) => Value::Short(n as i16),
+        Value::Long(_) => Value::Long(n),
+        _ => Value::Int(n as i32),
+    }
+}
+
+fn table(v: &mut Value, table: &[Dir], t: Transform) {
+    if let Some(d) = int_of(v).and_then(|i| table.get(i as usize)) {
+        let new = t.dir(*d);
+        if let Some(i) = table.iter().position(|&d| d == new) {
+            set_int(v, i as i64);
+        }
+    }
+}
+
+fn bits(v: &mut Value, table: &[Dir], t: Transform) {
+    if let Some(old) = int_of(v) {
+        let mut new = old & !((1 << table.len()) - 1);
+        for (bit, &d) in table.iter().enumerate() {
+            if old & (1 << bit) != 0 {
+                new |= 1 << table.iter().position(|&n| n == t.dir(d)).unwrap();
+            }
+        }
+        set_int(v, new);
+    }
+}
+
+fn string(v: &mut Value, t: Transform) {
+    if let Value::String(s) = v {
+        if let Some(d) = dir_of(s) {
+            *s = name_of(t.dir(d)).to_owned();
+        }
+    }
+}
+
+fn axis(v: &mut Value, t: Transform) {
+    if let Value::String(s) = v {
+        if t.swaps_axes() {
+            match s.as_str() {
+                "x" => *s = "z".to_owned(),
+                "z" => *s = "x".to_owned(),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn sign(v: &mut Value, t: Transform) {
+    if let Some(old) = int_of(v) {
+        let new = match t {
+            Transform::Rotate(_) => old + 4 * t.quarter_turns() as i64,
+            Transform::Mirror(Mirror::X) => 16 - old,
+            Transform::Mirror(Mirror::Z) => 8 - old,
+        };
+        set_int(v, new.rem_euclid(16));
+    }
+}
+
+fn rail(v: &mut Value, t: Transform) {
+    if let Some(&(slope, a, b)) = int_of(v).and_then(|i| RAILS.get(i as usize)) {
+        let (a, b) = (t.dir(a), t.dir(b));
+        if let Some(i) = RAILS
+            .iter()
+            .position(|&(s, x, y)| s == slope && ((x, y) == (a, b) || (x, y) == (b, a)))
+        {
+            set_int(v, i as i64);
+        }
+    }
+}
+
+fn lever(v: &mut Value, t: Transform) {
+    if let Value::String(s) = v {
+        if let Some((face, axis)) = s.split_once('_') {
+            if t.swaps_axes() {
+                let axis = match axis {
+                    "north_south" => "east_west",
+                    "east_west" => "north_south",
+                    a => a,
+                };
+                *s = format!("{face}_{axis}");
+            }
+        } else if let Some(d) = dir_of(s) {
+            *s = name_of(t.dir(d)).to_owned();
+        }
+    }
+}
+
+///改写方块状态中与朝向有关的值, 保持原有的标签类型与键的顺序
+pub(crate) fn transform_states(name: &str, states: &mut Map<String, Value>, t: Transform) {
+    let walls = [North, South, West, East].map(|d| {
+        let key = format!("wall_connection_type_{}", name_of(d));
+        (d, states.get(&key).cloned())
+    });
+    for (d, old) in &walls {
+        if let Some(old) = old {
+            let key = format!("wall_connection_type_{}", name_of(t.dir(*d)));
+            if let Some(v) = states.get_mut(&key) {
+                *v = old.clone();
+            }
+        }
+    }
+
+    for (key, v) in states.iter_mut() {
+        match key.as_str() {
+            "facing_direction" => table(v, &FACING, t),
+            "direction" => {
+                let encoding = if name.contains("trapdoor") {
+                    &WEIRDO_DIRECTION
+                } else if name.ends_with("door") {
+                    &DOOR_DIRECTION
+                } else {
+                    &DIRECTION
+                };
+                table(v, encoding, t)
+            }
+            "weirdo_direction" => table(v, &WEIRDO_DIRECTION, t),
+            "coral_direction" => table(v, &CORAL_DIRECTION, t),
+            "torch_facing_direction"
+            | "minecraft:facing_direction"
+            | "minecraft:cardinal_direction"
+            | "minecraft:block_face" => string(v, t),
+            "pillar_axis" | "portal_axis" => axis(v, t),
+            "ground_sign_direction" => sign(v, t),
+            "rail_direction" => rail(v, t),
+            "vine_direction_bits" => bits(v, &VINE_BITS, t),
+            "multi_face_direction_bits" => bits(v, &MULTI_FACE_BITS, t),
+            "lever_direction" => lever(v, t),
+            "door_hinge_bit" if matches!(t, Transform::Mirror(_)) => {
+                if let Some(hinge) = int_of(v) {
+                    set_int(v, 1 - hinge)
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! 结构的合法性检查与修复
+use super::{position_of, McStructure, StateType};
+use crate::Value;
+use std::fmt::Display;
+
+///[`McStructure::validate`]发现的问题
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    ///问题所在的路径, 与[`Value::get_path`]一致
+    pub path: String,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    ///`size`中存在负数, 无法修复, 此时也不会修复与方块下标和坐标有关的问题
+    NegativeSize,
+    ///`block_indices`某一层的长度与`size`的乘积不符, 修复时截断或以-1填充
+    LayerLength {
+        layer: usize,
+        expected: usize,
+        found: usize,
+    },
+    ///调色板下标越界, 修复时设为-1
+    PaletteIndex {
+        layer: usize,
+        index: usize,
+        value: i32,
+    },
+    ///`block_position_data`的键超出范围, 修复时移除
+    DataKey(i32),
+    ///方块实体的`x`, `y`, `z`与所在位置不符, 修复时改为所在位置
+    BlockEntityPosition {
+        key: i32,
+        expected: [i32; 3],
+        found: [Option<i32>; 3],
+    },
+    ///实体位于结构范围之外, 修复时移除
+    EntityOutside { entity: usize, pos: [f64; 3] },
+    ///方块状态的值不是`TAG_Byte`, `TAG_Int`或`TAG_String`, 修复时移除该状态
+    StateType {
+        palette: usize,
+        key: String,
+        found: &'static str,
+    },
+    ///方块状态的类型与类型表不符, 修复时转换为正确的类型, 无法转换时不修复
+    StateValue {
+        palette: usize,
+        key: String,
+        expected: StateType,
+        found: &'static str,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::NegativeSize => write!(f, "结构大小为负数"),
+            Problem::LayerLength {
+                layer,
+                expected,
+                found,
+            } => write!(f, "第{layer}层的长度为{found}, 应为{expected}"),
+            Problem::PaletteIndex { value, .. } => write!(f, "调色板下标{value}越界"),
+            Problem::DataKey(key) => write!(f, "方块下标{key}超出范围"),
+            Problem::BlockEntityPosition {
+                expected, found, ..
+            } => write!(f, "方块实体坐标为{found:?}, 应为{expected:?}"),
+            Problem::EntityOutside { pos, .. } => write!(f, "实体坐标{pos:?}不在结构范围内"),
+            Problem::StateType { key, found, .. } => {
+                write!(f, "方块状态{key}的类型{found}无效")
+            }
+            Problem::StateValue {
+                key,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "方块状态{key}的类型为{found}, 应为{}",
+                expected.tag_name()
+            ),
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.problem)
+    }
+}
+
+impl McStructure {
+    ///检查结构是否能被游戏正确加载
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let mut push = |path: String, problem| issues.push(Issue { path, problem });
+
+        if self.size.iter().any(|&v| v < 0) {
+            push("size".to_owned(), Problem::NegativeSize);
+        }
+        let volume = self.volume();
+        for (layer, indices) in self.block_indices.iter().enumerate() {
+            if indices.len() != volume {
+                push(
+                    format!("structure/block_indices/{layer}"),
+                    Problem::LayerLength {
+                        layer,
+                        expected: volume,
+                        found: indices.len(),
+                    },
+                );
+            }
+            for (index, &value) in indices.iter().enumerate() {
+                if value < -1 || value >= self.block_palette.len() as i32 {
+                    push(
+                        format!("structure/block_indices/{layer}/{index}"),
+                        Problem::PaletteIndex {
+                            layer,
+                            index,
+                            value,
+                        },
+                    );
+                }
+            }
+        }
+
+        let origin = self.structure_world_origin;
+        for (&key, data) in &self.block_position_data {
+            let path = format!("structure/palette/default/block_position_data/{key}");
+            if key < 0 || key as usize >= volume {
+                push(path, Problem::DataKey(key));
+                continue;
+            }
+            if let Some(Value::Compound(entity)) = data.get("block_entity_data") {
+                let pos = position_of(self.size, key as usize);
+                let expected = [0, 1, 2].map(|i| origin[i] + pos[i]);
+                let found = ["x", "y", "z"].map(|k| entity.get(k).and_then(Value::to_i32));
+                if found.iter().any(Option::is_some)
+                    && found.iter().zip(expected).any(|(f, e)| *f != Some(e))
+                {
+                    push(
+                        format!("{path}/block_entity_data"),
+                        Problem::BlockEntityPosition {
+                            key,
+                            expected,
+                            found,
+                        },
+                    );
+                }
+            }
+        }
+
+        for (entity, value) in self.entities.iter().enumerate() {
+            let Some(Value::List(list)) = value.get("Pos") else {
+                continue;
+            };
+            let pos = match list.as_slice() {
+                [x, y, z] => [x.to_f64(), y.to_f64(), z.to_f64()],
+                _ => continue,
+            };
+            let [Some(x), Some(y), Some(z)] = pos else {
+                continue;
+            };
+            let pos = [x, y, z];
+            if (0..3)
+                .any(|i| pos[i] < origin[i] as f64 || pos[i] >= (origin[i] + self.size[i]) as f64)
+            {
+                push(
+                    format!("structure/entities/{entity}/Pos"),
+                    Problem::EntityOutside { entity, pos },
+                );
+            }
+        }
+
+        for (palette, block) in self.block_palette.iter().enumerate() {
+            for (key, value) in &block.states {
+                let path =
+                    format!("structure/palette/default/block_palette/{palette}/states/{key}");
+                let (key, found) = (key.clone(), value.tag_name());
+                let expected = StateType::of(&key);
+                if !matches!(value, Value::Byte(_) | Value::Int(_) | Value::String(_)) {
+                    push(
+                        path,
+                        Problem::StateType {
+                            palette,
+                            key,
+                            found,
+                        },
+                    );
+                } else if let Some(expected) = expected.filter(|t| t.tag_name() != found) {
+                    push(
+                        path,
+                        Problem::StateValue {
+                            palette,
+                            key,
+                            expected,
+                            found,
+                        },
+                    );
+                }
+            }
+        }
+        issues
+    }
+
+    ///修复[`Self::validate`]发现的问题, 返回已修复的问题, 无法修复的问题不会包含在内.
+    ///`size`存在负数时无法确定结构的范围, 不修复与方块下标和坐标有关的问题
+    pub fn repair(&mut self) -> Vec<Issue> {
+        let mut issues = self.validate();
+        let valid_size = self.size.iter().all(|&v| v >= 0);
+        issues.retain(|i| match &i.problem {
+            Problem::NegativeSize => false,
+            Problem::LayerLength { .. }
+            | Problem::PaletteIndex { .. }
+            | Problem::DataKey(_)
+            | Problem::EntityOutside { .. } => valid_size,
+            Problem::StateValue {
+                palette,
+                key,
+                expected,
+                ..
+            } => expected
+                .coerce(&self.block_palette[*palette].states[key])
+                .is_some(),
+            _ => true,
+        });
+        let volume = self.volume();
+        let mut removed_entities = Vec::new();
+
+        for issue in &issues {
+            match &issue.problem {
+                Problem::LayerLength { layer, .. } => self.block_indices[*layer].resize(volume, -1),
+                Problem::PaletteIndex { layer, index, .. } => {
+                    if let Some(v) = self.block_indices[*layer].get_mut(*index) {
+                        *v = -1;
+                    }
+                }
+                Problem::DataKey(key) => {
+                    self.block_position_data.remove(key);
+                }
+                Problem::BlockEntityPosition { key, expected, .. } => {
+                    let entity = self
+                        .block_position_data
+                        .get_mut(key)
+                        .and_then(|d| d.get_mut("block_entity_data"))
+                        .and_then(Value::as_compound_mut);
+                    if let Some(entity) = entity {
+                        for (k, v) in ["x", "y", "z"].into_iter().zip(expected) {
+                            entity.insert(k.to_owned(), Value::Int(*v));
+                        }
+                    }
+                }
+                Problem::EntityOutside { entity, .. } => removed_entities.push(*entity),
+                Problem::StateType { palette, key, .. } => {
+                    self.block_palette[*palette].states.remove(key);
+                }
+                Problem::StateValue {
+                    palette,
+                    key,
+                    expected,
+                    ..
+                } => {
+                    if let Some(value) = self.block_palette[*palette].states.get_mut(key) {
+                        *value = expected.coerce(value).unwrap();
+                    }
+                }
+                Problem::NegativeSize => {}
+            }
+        }
+        for entity in removed_entities.into_iter().rev() {
+            self.entities.remove(entity);
+        }
+        issues
+    }
+}
@@ -17,6 +17,1351 @@ fn write_gold_farm_with_header() -> IResult<()> {
     let mut buf: Vec<u8> = vec![];
     let mut f = std::fs::File::create("res/out.nbt")?;
     NBT::from_reader(&mut File::open("res/example.nbt")?)?.write(&mut buf, true)?;
-    f.write_all(&mut buf)?;
+    f.write_all(&buf)?;
+    Ok(())
+}
+
+#[test]
+fn nbt_macro() -> IResult<()> {
+    use crate::{nbt, Map, Value};
+    let x = 64;
+    let v = nbt!({
+        "Name": "minecraft:chest",
+        Count: 1i8,
+        "Pos": [0i32, x, -1],
+        "Items": [{ "Slot": 0i8 }, {}],
+        "Nested": [[1i16], []],
+        "Heights": [I; 1, 2],
+        "Motion": [-0.5f32, 0.0],
+    });
+    let mut map = Map::new();
+    map.insert("Name".to_string(), Value::String("minecraft:chest".into()));
+    map.insert("Count".to_string(), Value::Byte(1));
+    map.insert(
+        "Pos".to_string(),
+        Value::List(vec![Value::Int(0), Value::Int(64), Value::Int(-1)]),
+    );
+    let mut slot = Map::new();
+    slot.insert("Slot".to_string(), Value::Byte(0));
+    map.insert(
+        "Items".to_string(),
+        Value::List(vec![Value::Compound(slot), Value::Compound(Map::new())]),
+    );
+    map.insert(
+        "Nested".to_string(),
+        Value::List(vec![
+            Value::List(vec![Value::Short(1)]),
+            Value::List(vec![]),
+        ]),
+    );
+    map.insert("Heights".to_string(), Value::IntArray(vec![1, 2]));
+    map.insert(
+        "Motion".to_string(),
+        Value::List(vec![Value::Float(-0.5), Value::Float(0.0)]),
+    );
+    assert_eq!(v, Value::Compound(map));
+
+    let mut buf = vec![];
+    nbt!("" => { "a": 1 }).write(&mut buf, false)?;
+    assert_eq!(NBT::new(&mut buf)?.data, nbt!({ "a": 1 }));
+    Ok(())
+}
+
+#[test]
+fn value_accessors() -> IResult<()> {
+    use crate::{nbt, Error, Value};
+    use std::fs::File;
+    let n = NBT::from_reader(&mut File::open("res/gold_farm.mcstructure")?)?;
+    let data = &n.data;
+    assert_eq!(data["format_version"].as_int(), Some(1));
+    assert_eq!(
+        data.get_path("structure/palette/default/block_palette/1/name")
+            .and_then(Value::as_str),
+        Some("minecraft:stonebrick")
+    );
+    assert_eq!(data["size"][2], Value::Int(9));
+    assert_eq!(data.get_as::<i32>("size/0")?, 28);
+
+    let v = nbt!({ "a": 1i8, "b": 2i16 });
+    assert_eq!(i8::try_from(v["a"].clone())?, 1);
+    assert!(i32::try_from(v["a"].clone()).is_err());
+    assert_eq!(v["a"].to_i64(), Some(1));
+    assert_eq!(v["b"].to_f64(), Some(2.0));
+    assert_eq!(v["b"].to_i16(), Some(2));
+    assert_eq!(v["b"].as_byte(), None);
+    assert_eq!(v.get_widened::<i64>("b")?, 2);
+    match v.get_as::<i32>("b") {
+        Err(Error::Type {
+            expected,
+            found,
+            path,
+        }) => assert_eq!(
+            (expected, found, path.as_str()),
+            ("TAG_Int", "TAG_Short", "b")
+        ),
+        r => panic!("{r:?}"),
+    }
+    assert!(matches!(v.get_as::<i32>("c"), Err(Error::MissingKey(_))));
+
+    let list: Value = (0..3).map(Value::from).collect();
+    assert_eq!(list, nbt!([0, 1, 2]));
+    let compound: Value = [("x", Value::from(1))].into_iter().collect();
+    assert_eq!(compound, nbt!({ "x": 1 }));
+    Ok(())
+}
+
+#[test]
+fn visit_and_walk() -> IResult<()> {
+    use crate::visit::{walk_value, walk_value_mut, Visit, VisitMut};
+    use crate::Value;
+    use std::collections::HashMap;
+    use std::fs::File;
+    let mut n = NBT::from_reader(&mut File::open("res/gold_farm.mcstructure")?)?;
+
+    #[derive(Default)]
+    struct Count(HashMap<&'static str, usize>);
+    impl Visit for Count {
+        fn visit_value(&mut self, path: &str, value: &Value) {
+            *self.0.entry(value.tag_name()).or_default() += 1;
+            walk_value(self, path, value);
+        }
+    }
+    let mut count = Count::default();
+    n.data.visit(&mut count);
+    assert_eq!(count.0.values().sum::<usize>(), n.data.walk().count());
+    assert_eq!(
+        count.0["TAG_Compound"],
+        n.data
+            .walk()
+            .filter(|(_, v)| v.as_compound().is_some())
+            .count()
+    );
+
+    struct Rename;
+    impl VisitMut for Rename {
+        fn visit_value_mut(&mut self, path: &str, value: &mut Value) {
+            if value.as_str() == Some("minecraft:stonebrick") {
+                *value = Value::from("minecraft:mossy_stone_bricks");
+            }
+            walk_value_mut(self, path, value);
+        }
+    }
+    n.data.visit_mut(&mut Rename);
+    let (path, _) = n
+        .data
+        .walk()
+        .find(|(_, v)| v.as_str() == Some("minecraft:mossy_stone_bricks"))
+        .unwrap();
+    assert_eq!(path, "structure/palette/default/block_palette/1/name");
+    assert!(n
+        .data
+        .walk()
+        .all(|(_, v)| v.as_str() != Some("minecraft:stonebrick")));
+    Ok(())
+}
+
+#[test]
+fn encoded_len_matches_write() -> IResult<()> {
+    use crate::{nbt, Value};
+    use std::fs::File;
+    let n = NBT::from_reader(&mut File::open("res/gold_farm.mcstructure")?)?;
+    let bytes = std::fs::read("res/gold_farm.mcstructure")?;
+    for header in [false, true] {
+        let mut buf = vec![];
+        n.write(&mut buf, header)?;
+        assert_eq!(n.encoded_len(header), buf.len());
+    }
+    let mut buf = vec![];
+    n.write(&mut buf, false)?;
+    assert_eq!(buf, bytes);
+
+    let v = nbt!({ "名称": "箱子", "a": [B; 1, 2], "l": [[I; 1], [I;]], "e": [] });
+    let mut buf = vec![];
+    v.write(&mut buf)?;
+    assert_eq!(v.encoded_len(), buf.len());
+
+    //嵌套的列表元素类型不一致时不写入任何内容
+    let mut data = nbt!({ "a": 1, "l": [{ "m": [1] }] });
+    if let Value::List(m) = &mut data["l"][0]["m"] {
+        m.push(Value::from("x"));
+    }
+    let mut buf = vec![];
+    let n = NBT {
+        name: String::new(),
+        data,
+    };
+    assert!(matches!(
+        n.write(&mut buf, true),
+        Err(crate::Error::HeterogeneousList)
+    ));
+    assert!(buf.is_empty());
+    Ok(())
+}
+
+#[test]
+fn mcstructure_round_trip() -> IResult<()> {
+    use crate::structure::McStructure;
+    use crate::{nbt, Value};
+    let bytes = std::fs::read("res/gold_farm.mcstructure")?;
+    let mut s = McStructure::read(&mut bytes.as_slice())?;
+    assert_eq!(s.size, [28, 50, 9]);
+    assert_eq!(s.structure_world_origin, [467, 79, 625]);
+    assert_eq!(s.block_indices[0].len(), 28 * 50 * 9);
+    assert_eq!(s.block_palette[1].name, "minecraft:stonebrick");
+    assert_eq!(
+        s.block_position_data[&10365]["block_entity_data"]["id"],
+        Value::from("Sign")
+    );
+    let mut buf = vec![];
+    s.write(&mut buf)?;
+    assert_eq!(buf, bytes);
+
+    s.unknown.root.insert("custom".into(), nbt!({ "a": 1 }));
+    s.block_palette[0]
+        .other
+        .insert("extra".into(), Value::Byte(1));
+    let mut buf = vec![];
+    s.write(&mut buf)?;
+    assert_eq!(McStructure::read(&mut buf.as_slice())?, s);
+
+    //未被识别的键按字节序排在已知的键之间, 写回的字节与原文件相同
+    let mut nbt = crate::NBT::from_reader(&mut bytes.as_slice())?;
+    for (path, key) in [
+        ("", "custom"),
+        ("structure", "a_extra"),
+        ("structure/palette", "backup"),
+        ("structure/palette/default", "a_extra"),
+        ("structure/palette/default/block_palette/0", "extra"),
+    ] {
+        let map = match path {
+            "" => nbt.data.as_compound_mut(),
+            path => nbt.data.get_path_mut(path).and_then(Value::as_compound_mut),
+        }
+        .unwrap();
+        map.insert(key.to_owned(), Value::Byte(1));
+        crate::compound::sort_keys(map);
+    }
+    let mut unknown = vec![];
+    nbt.write(&mut unknown, false)?;
+    let mut buf = vec![];
+    McStructure::read(&mut unknown.as_slice())?.write(&mut buf)?;
+    assert_eq!(buf, unknown);
+
+    //没有`block_position_data`的结构
+    let mut nbt = crate::NBT::from_reader(&mut bytes.as_slice())?;
+    nbt.data
+        .get_path_mut("structure/palette/default")
+        .and_then(Value::as_compound_mut)
+        .unwrap()
+        .remove("block_position_data");
+    let mut bytes = vec![];
+    nbt.write(&mut bytes, false)?;
+    let s = McStructure::read(&mut bytes.as_slice())?;
+    assert!(s.block_position_data.is_empty());
+    let mut buf = vec![];
+    s.write(&mut buf)?;
+    assert_eq!(buf, bytes);
+    Ok(())
+}
+
+#[test]
+fn structure_get_set_block() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure};
+    use crate::Value;
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let sign = s.get_block(23, 1, 6).unwrap();
+    assert!(sign.name.ends_with("sign"), "{}", sign.name);
+    assert_eq!(s.index(23, 1, 6), Some(10365));
+    assert_eq!(s.position(10365), [23, 1, 6]);
+    assert_eq!(s.index(28, 0, 0), None);
+
+    let len = s.block_palette.len();
+    let mut stone = BlockState::new("minecraft:stonebrick");
+    stone
+        .states
+        .insert("stone_brick_type".into(), Value::from("default"));
+    s.set_block(23, 1, 6, stone.clone())?;
+    assert_eq!(s.block_palette.len(), len);
+    assert!(s.get_block(23, 1, 6).unwrap().same_block(&stone));
+    assert!(!s.block_position_data.contains_key(&10365));
+
+    s.set_block(0, 0, 0, BlockState::new("minecraft:diamond_block"))?;
+    assert_eq!(s.block_palette.len(), len + 1);
+    s.set_secondary_block(0, 0, 0, Some(BlockState::new("minecraft:water")))?;
+    assert_eq!(
+        s.get_secondary_block(0, 0, 0).unwrap().name,
+        "minecraft:water"
+    );
+    s.set_block(0, 0, 0, BlockState::new("minecraft:diamond_block"))?;
+    assert_eq!(s.get_secondary_block(0, 0, 0), None);
+    s.remove_block(0, 0, 0)?;
+    assert_eq!(s.get_block(0, 0, 0), None);
+    assert!(s.set_block(0, 50, 0, stone).is_err());
+    Ok(())
+}
+
+#[test]
+fn structure_rotate_and_mirror() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure, Mirror, Rotation};
+    use crate::{nbt, Value};
+    let original = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let mut s = original.clone();
+    s.rotate(Rotation::Clockwise90);
+    assert_eq!(s.size, [9, 50, 28]);
+    let sign = s.index(2, 1, 23).unwrap() as i32;
+    let entity = &s.block_position_data[&sign]["block_entity_data"];
+    assert_eq!(entity["x"], Value::Int(467 + 2));
+    assert_eq!(entity["z"], Value::Int(625 + 23));
+    for _ in 0..3 {
+        s.rotate(Rotation::Clockwise90);
+    }
+    assert_eq!(s, original);
+    s.mirror(Mirror::X);
+    s.mirror(Mirror::X);
+    assert_eq!(s, original);
+
+    let mut s = McStructure::new([2, 1, 3]);
+    s.structure_world_origin = [10, 0, 10];
+    let block = |name: &str, states: Value| {
+        let mut b = BlockState::new(name);
+        b.states = states.as_compound().unwrap().clone();
+        b
+    };
+    s.set_block(
+        0,
+        0,
+        0,
+        block("minecraft:observer", nbt!({ "facing_direction": 3 })),
+    )?;
+    s.set_block(
+        1,
+        0,
+        0,
+        block(
+            "minecraft:oak_stairs",
+            nbt!({ "weirdo_direction": 0, "upside_down_bit": 0i8 }),
+        ),
+    )?;
+    s.set_block(
+        0,
+        0,
+        1,
+        block("minecraft:log", nbt!({ "pillar_axis": "x" })),
+    )?;
+    s.set_block(
+        1,
+        0,
+        1,
+        block(
+            "minecraft:cobblestone_wall",
+            nbt!({ "wall_connection_type_east": "short", "wall_connection_type_north": "none",
+                   "wall_connection_type_south": "tall", "wall_connection_type_west": "none" }),
+        ),
+    )?;
+    //大箱子, 另一半位于(1, 0, 2)
+    s.set_block(0, 0, 2, BlockState::new("minecraft:chest"))?;
+    s.set_block(1, 0, 2, BlockState::new("minecraft:chest"))?;
+    s.block_position_data.insert(
+        s.index(0, 0, 2).unwrap() as i32,
+        crate::Map::try_from(nbt!({
+            "block_entity_data": { "id": "Chest", "pairlead": 1i8, "pairx": 11, "pairz": 12,
+                                   "x": 10, "y": 0, "z": 12 }
+        }))?,
+    );
+    let chest = |s: &McStructure, x, z| {
+        let data = &s.block_position_data[&(s.index(x, 0, z).unwrap() as i32)];
+        let e = &data["block_entity_data"];
+        [&e["x"], &e["z"], &e["pairx"], &e["pairz"]].map(|v| v.to_i32().unwrap())
+    };
+    s.entities.push(
+        nbt!({ "Pos": [10.5f32, 0.0, 10.5], "Rotation": [0f32, 0.0] })
+            .as_compound()
+            .unwrap()
+            .clone(),
+    );
+    s.rotate(Rotation::Clockwise90);
+    assert_eq!(s.size, [3, 1, 2]);
+    let states =
+        |s: &McStructure, x, z| Value::Compound(s.get_block(x, 0, z).unwrap().states.clone());
+    assert_eq!(states(&s, 2, 0), nbt!({ "facing_direction": 4 }));
+    assert_eq!(
+        states(&s, 2, 1),
+        nbt!({ "weirdo_direction": 2, "upside_down_bit": 0i8 })
+    );
+    assert_eq!(states(&s, 1, 0), nbt!({ "pillar_axis": "z" }));
+    assert_eq!(
+        states(&s, 1, 1),
+        nbt!({ "wall_connection_type_east": "none", "wall_connection_type_north": "none",
+               "wall_connection_type_south": "short", "wall_connection_type_west": "tall" })
+    );
+    assert_eq!(
+        Value::Compound(s.entities[0].clone()),
+        nbt!({ "Pos": [12.5f32, 0.0, 10.5], "Rotation": [90f32, 0.0] })
+    );
+
+    assert_eq!(chest(&s, 0, 0), [10, 10, 10, 11]);
+
+    s.mirror(Mirror::X);
+    assert_eq!(states(&s, 0, 0), nbt!({ "facing_direction": 5 }));
+    assert_eq!(chest(&s, 2, 0), [12, 10, 12, 11]);
+    Ok(())
+}
+
+#[test]
+fn structure_crop_expand_trim() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure, Padding};
+    use crate::{nbt, Value};
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let sign = s.get_block(23, 1, 6).cloned();
+    s.crop([20, 0, 5], [8, 10, 4])?;
+    assert_eq!(s.size, [8, 10, 4]);
+    assert_eq!(s.structure_world_origin, [487, 79, 630]);
+    assert_eq!(s.get_block(3, 1, 1).cloned(), sign);
+    let key = s.index(3, 1, 1).unwrap() as i32;
+    assert_eq!(
+        s.block_position_data[&key]["block_entity_data"]["x"],
+        Value::Int(490)
+    );
+    assert!(s.crop([0, 0, 0], [9, 1, 1]).is_err());
+
+    let mut s = McStructure::new([3, 3, 3]);
+    s.structure_world_origin = [100, 64, 100];
+    s.set_block(1, 1, 1, BlockState::new("minecraft:stone"))?;
+    s.set_block(1, 2, 1, BlockState::new("minecraft:air"))?;
+    s.entities.push(
+        nbt!({ "Pos": [101.5f32, 65.0, 101.5] })
+            .as_compound()
+            .unwrap()
+            .clone(),
+    );
+    s.entities.push(
+        nbt!({ "Pos": [100.5f32, 64.0, 100.5] })
+            .as_compound()
+            .unwrap()
+            .clone(),
+    );
+    assert_eq!(s.bounding_box(false), Some(([1, 1, 1], [1, 2, 1])));
+    let mut air = s.clone();
+    air.trim(true);
+    assert_eq!(air.size, [1, 1, 1]);
+    assert_eq!(air.structure_world_origin, [101, 65, 101]);
+    assert_eq!(air.entities.len(), 1);
+
+    air.expand([1, 0, 0], [0, 0, 2], Padding::Air);
+    assert_eq!(air.size, [2, 1, 3]);
+    assert_eq!(air.structure_world_origin, [100, 65, 101]);
+    assert_eq!(air.get_block(1, 0, 0).unwrap().name, "minecraft:stone");
+    assert!(air.get_block(0, 0, 2).unwrap().is_air());
+
+    //没有扩展时不改变调色板
+    let mut stone = McStructure::new([1, 1, 1]);
+    stone.set_block(0, 0, 0, BlockState::new("minecraft:stone"))?;
+    let unchanged = stone.clone();
+    stone.expand([0; 3], [0; 3], Padding::Air);
+    assert_eq!(stone, unchanged);
+
+    s.expand([0; 3], [1; 3], Padding::StructureVoid);
+    assert_eq!(s.size, [4, 4, 4]);
+    assert_eq!(s.get_block(3, 3, 3), None);
+    Ok(())
+}
+
+#[test]
+fn structure_paste() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure, PasteMode};
+    use crate::{nbt, Value};
+    let mut module = McStructure::new([2, 1, 1]);
+    module.structure_world_origin = [50, 0, 50];
+    module.set_block(0, 0, 0, BlockState::new("minecraft:chest"))?;
+    module.set_block(1, 0, 0, BlockState::new("minecraft:air"))?;
+    module.block_position_data.insert(
+        0,
+        nbt!({ "block_entity_data": { "id": "Chest", "pairx": 51, "pairz": 50, "x": 50, "y": 0, "z": 50 } })
+            .as_compound()
+            .unwrap()
+            .clone(),
+    );
+    module.entities.push(
+        nbt!({ "Pos": [51.5f32, 0.0, 50.5] })
+            .as_compound()
+            .unwrap()
+            .clone(),
+    );
+
+    let mut base = McStructure::new([4, 2, 2]);
+    base.structure_world_origin = [0, 64, 0];
+    let stone = BlockState::new("minecraft:stone");
+    for x in 0..4 {
+        base.set_block(x, 0, 1, stone.clone())?;
+    }
+    let mut masked = base.clone();
+
+    base.paste(&module, [2, 0, 1], PasteMode::IgnoreVoid);
+    assert_eq!(base.get_block(2, 0, 1).unwrap().name, "minecraft:chest");
+    assert!(base.get_block(3, 0, 1).unwrap().is_air());
+    assert_eq!(base.block_palette.len(), 3);
+    let chest = base.index(2, 0, 1).unwrap() as i32;
+    assert_eq!(
+        Value::Compound(base.block_position_data[&chest].clone()),
+        nbt!({ "block_entity_data": { "id": "Chest", "pairx": 3, "pairz": 1, "x": 2, "y": 64, "z": 1 } })
+    );
+    assert_eq!(
+        Value::Compound(base.entities[0].clone()),
+        nbt!({ "Pos": [3.5f32, 64.0, 1.5] })
+    );
+
+    masked.paste(&module, [3, 0, 1], PasteMode::IgnoreAir);
+    assert_eq!(masked.get_block(3, 0, 1).unwrap().name, "minecraft:chest");
+    assert!(masked.entities.is_empty());
+    masked.paste(&McStructure::new([1, 1, 1]), [0, 0, 1], PasteMode::Replace);
+    assert_eq!(masked.get_block(0, 0, 1), None);
+    Ok(())
+}
+
+#[test]
+fn structure_compact_palette() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure};
+    use crate::Value;
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let original = s.clone();
+    let report = s.compact_palette();
+    assert_eq!(report.unused + report.duplicates, 0);
+    assert_eq!(s, original);
+
+    let mut s = McStructure::new([3, 1, 1]);
+    let mut a = BlockState::new("minecraft:stone_block_slab");
+    a.states
+        .insert("stone_slab_type".into(), Value::from("stone_brick"));
+    a.states.insert("top_slot_bit".into(), Value::Byte(0));
+    let mut b = BlockState::new("minecraft:stone_block_slab");
+    b.states.insert("top_slot_bit".into(), Value::Byte(0));
+    b.states
+        .insert("stone_slab_type".into(), Value::from("stone_brick"));
+    s.block_palette = vec![
+        BlockState::new("minecraft:dirt"),
+        a.clone(),
+        b,
+        BlockState::new("minecraft:water"),
+    ];
+    s.block_indices = [vec![1, 2, -1], vec![-1, 3, -1]];
+    let report = s.compact_palette();
+    assert_eq!((report.entries_before, report.entries_after), (4, 2));
+    assert_eq!((report.unused, report.duplicates), (1, 1));
+    assert!(report.bytes_saved > 0);
+    assert_eq!(s.block_indices, [vec![0, 0, -1], vec![-1, 1, -1]]);
+    assert_eq!(s.block_palette[0], a);
+    Ok(())
+}
+
+#[test]
+fn structure_validate_and_repair() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure, Problem};
+    use crate::{Map, Value};
+    let s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    assert!(s.validate().is_empty());
+
+    let mut s = McStructure::new([2, 1, 1]);
+    s.block_indices = [vec![0, 5], vec![-1]];
+    s.block_palette
+        .push(BlockState::new("minecraft:snow_layer"));
+    s.block_palette[0]
+        .states
+        .insert("height".into(), Value::Float(0.5));
+    s.block_position_data.insert(7, Map::new());
+    let mut pos = Map::new();
+    pos.insert(
+        "Pos".to_owned(),
+        Value::List(vec![
+            Value::Float(5.0),
+            Value::Float(0.5),
+            Value::Float(0.5),
+        ]),
+    );
+    s.entities.push(pos);
+    let issues = s.validate();
+    assert_eq!(issues.len(), 5);
+    assert!(issues
+        .iter()
+        .any(|i| i.path == "structure/block_indices/0/1"
+            && matches!(i.problem, Problem::PaletteIndex { value: 5, .. })));
+
+    assert_eq!(s.repair().len(), 5);
+    assert!(s.validate().is_empty());
+    assert_eq!(s.block_indices, [vec![0, -1], vec![-1, -1]]);
+    assert!(s.entities.is_empty() && s.block_position_data.is_empty());
+
+    //`size`为负数时不修改方块与`block_position_data`
+    let mut s = McStructure::new([2, 1, 1]);
+    s.block_position_data.insert(1, Map::new());
+    s.size = [2, -1, 1];
+    assert!(s.repair().is_empty());
+    assert_eq!(s.block_indices, [vec![-1, -1], vec![-1, -1]]);
+    assert_eq!(s.block_position_data.len(), 1);
+    assert!(matches!(s.validate()[0].problem, Problem::NegativeSize));
+    Ok(())
+}
+
+#[test]
+fn structure_materials() -> IResult<()> {
+    use crate::structure::McStructure;
+    let s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let m = s.materials(false);
+    assert_eq!(m.volume, 28 * 50 * 9);
+    assert!(m.non_air > 0 && m.non_air_ratio() < 1.0);
+    assert!(m.blocks.windows(2).all(|w| w[0].1 >= w[1].1));
+    assert!(m.blocks.iter().all(|(name, _)| name != "minecraft:air"));
+    let detailed = s.materials(true);
+    assert!(detailed.blocks.len() >= m.blocks.len());
+    let total = |v: &[(String, u64)]| v.iter().map(|(_, c)| c).sum::<u64>();
+    assert_eq!(total(&detailed.blocks), total(&m.blocks));
+
+    let csv = detailed.to_csv();
+    assert!(csv.starts_with("category,name,count\n"));
+    assert_eq!(
+        csv.lines().count(),
+        1 + detailed.blocks.len() + detailed.items.len() + detailed.entities.len()
+    );
+    let json = m.to_json();
+    assert!(json.starts_with("{\"volume\":12600,"));
+    assert!(json.contains("\"blocks\":{\"") && json.ends_with('}'));
+    Ok(())
+}
+
+#[test]
+fn structure_find_and_replace() -> IResult<()> {
+    use crate::structure::{BlockPattern, BlockState, McStructure};
+    use crate::Value;
+    let pattern: BlockPattern = "observer[\"facing_direction\"=3, powered_bit=false]".parse()?;
+    assert_eq!(pattern.name, "minecraft:observer");
+    assert_eq!(pattern.states["powered_bit"], Value::Byte(0));
+    assert!("observer[facing_direction]"
+        .parse::<BlockPattern>()
+        .is_err());
+    assert!("observer[facing_direction=3"
+        .parse::<BlockPattern>()
+        .is_err());
+
+    let observer = |facing: i32| {
+        let mut b = BlockState::new("minecraft:observer");
+        b.states
+            .insert("facing_direction".into(), Value::Int(facing));
+        b.states.insert("powered_bit".into(), Value::Byte(0));
+        b
+    };
+    let mut s = McStructure::new([3, 1, 1]);
+    s.set_block(0, 0, 0, observer(3))?;
+    s.set_block(1, 0, 0, observer(2))?;
+    s.set_block(2, 0, 0, observer(3))?;
+    assert_eq!(s.find(&pattern), vec![[0, 0, 0], [2, 0, 0]]);
+
+    let mut dispenser = BlockState::new("minecraft:dispenser");
+    dispenser
+        .states
+        .insert("facing_direction".into(), Value::Int(0));
+    dispenser
+        .states
+        .insert("triggered_bit".into(), Value::Byte(0));
+    let any: BlockPattern = "minecraft:observer".parse()?;
+    assert_eq!(s.replace(&any, &dispenser, true), 3);
+    assert_eq!(s.block_palette.len(), 2);
+    assert_eq!(
+        s.get_block(1, 0, 0).unwrap().states["facing_direction"],
+        Value::Int(2)
+    );
+    assert!(!s
+        .get_block(1, 0, 0)
+        .unwrap()
+        .states
+        .contains_key("powered_bit"));
+    assert!(s.find(&any).is_empty());
+    Ok(())
+}
+
+#[test]
+fn structure_shapes() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure};
+    let stone = BlockState::new("minecraft:stone");
+    let glass = BlockState::new("minecraft:glass");
+    let count = |s: &McStructure, name: &str| {
+        s.block_indices[0]
+            .iter()
+            .filter(|&&i| i >= 0 && s.block_palette[i as usize].name == name)
+            .count()
+    };
+
+    let mut s = McStructure::new([5, 5, 5]);
+    assert_eq!(s.fill([-2, 0, 0], [10, 0, 4], &stone), 25);
+    assert_eq!(s.hollow([0, 0, 0], [4, 4, 4], &glass), 125);
+    assert_eq!(
+        (count(&s, "minecraft:glass"), count(&s, "minecraft:air")),
+        (98, 27)
+    );
+    assert_eq!(s.outline([4, 4, 4], [0, 0, 0], &stone), 98);
+    assert_eq!(s.walls([0, 0, 0], [4, 4, 4], &glass), 80);
+    assert_eq!(count(&s, "minecraft:stone"), 18);
+
+    let mut s = McStructure::new([7, 7, 7]);
+    assert_eq!(s.sphere([3, 3, 3], 1.0, &stone, false), 7);
+    let solid = s.sphere([3, 3, 3], 3.0, &stone, false);
+    let mut hollow = McStructure::new([7, 7, 7]);
+    let shell = hollow.sphere([3, 3, 3], 3.0, &stone, true);
+    assert!(shell < solid && hollow.get_block(3, 3, 3).is_none());
+    assert_eq!(hollow.cylinder([3, 0, 3], 1.0, 3, &glass, false), 15);
+    assert_eq!(s.cylinder([3, 0, 3], 0.0, 10, &glass, true), 7);
+    //半径极大时只填充结构范围内的方块
+    let mut huge = McStructure::new([2, 2, 2]);
+    assert_eq!(huge.sphere([0, 0, 0], 1e12, &stone, false), 8);
+    assert_eq!(
+        huge.cylinder([1, 0, 1], f64::MAX, i32::MAX, &stone, true),
+        0
+    );
+    //没有内部时不加入空气
+    let mut thin = McStructure::new([2, 2, 2]);
+    assert_eq!(thin.hollow([0, 0, 0], [1, 1, 1], &glass), 8);
+    assert!(thin.block_palette.iter().all(|b| !b.is_air()));
+
+    let mut s = McStructure::new([4, 4, 4]);
+    assert_eq!(s.line([0, 0, 0], [3, 3, 3], &stone), 4);
+    assert_eq!(s.line([3, 0, 0], [0, 1, 9], &stone), 4);
+    assert!(s.get_block(2, 2, 2).is_some());
+    Ok(())
+}
+
+#[test]
+fn structure_to_commands() -> IResult<()> {
+    use crate::structure::{ExportOptions, McStructure};
+    use crate::{nbt, NbtValue};
+    let mut s = McStructure::new([3, 2, 40]);
+    let stone = NbtValue::from_value(nbt!({name: "minecraft:stone", states: {}}))?;
+    let observer = NbtValue::from_value(nbt!({
+        name: "minecraft:observer",
+        states: {facing_direction: 3, powered_bit: 0i8, "minecraft:block_face": "up"}
+    }))?;
+    s.fill([0, 0, 0], [2, 0, 39], &stone);
+    s.set_block(1, 1, 1, observer)?;
+
+    let commands = s.to_commands(&ExportOptions::default());
+    assert_eq!(
+        commands,
+        [
+            "fill ~ ~ ~ ~2 ~ ~39 minecraft:stone",
+            "setblock ~1 ~1 ~1 minecraft:observer [\"facing_direction\"=3,\"powered_bit\"=false,\"minecraft:block_face\"=\"up\"]",
+        ]
+    );
+    let options = ExportOptions {
+        origin: Some([100, 64, -20]),
+        max_commands: 1,
+        ..Default::default()
+    };
+    let files = s.to_mcfunctions(&options);
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0], "fill 100 64 -20 102 64 19 minecraft:stone\n");
+
+    //字符串中的引号与反斜杠会被转义, 旧版本的数据值会被输出
+    let mut s = McStructure::new([2, 1, 1]);
+    let sign = NbtValue::from_value(nbt!({ name: "minecraft:sign", states: { text: "a\"b\\c" } }))?;
+    s.set_block(0, 0, 0, sign)?;
+    s.set_block(
+        1,
+        0,
+        0,
+        NbtValue::from_value(nbt!({ name: "minecraft:wool", val: 14i16 }))?,
+    )?;
+    let commands = s.to_commands(&ExportOptions::default());
+    assert_eq!(
+        commands[0],
+        r#"setblock ~ ~ ~ minecraft:sign ["text"="a\"b\\c"]"#
+    );
+    assert_eq!(commands[1], "setblock ~1 ~ ~ minecraft:wool 14");
+    let imported = McStructure::from_commands(&commands.join("\n"))?;
+    assert_eq!(imported.block_palette.len(), 2);
+    assert!(imported
+        .block_palette
+        .iter()
+        .zip(&s.block_palette)
+        .all(|(a, b)| a.same_block(b)));
+
+    let s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let commands = s.to_commands(&ExportOptions::default());
+    let blocks = s.block_indices[0].iter().filter(|&&i| i >= 0).count();
+    assert!(!commands.is_empty() && commands.len() < blocks);
+    Ok(())
+}
+
+#[test]
+fn structure_from_commands() -> IResult<()> {
+    use crate::structure::{ExportOptions, McStructure};
+    use crate::Value;
+    let src = "# comment\n\
+        /fill 10 64 10 14 68 14 stone hollow\n\
+        say hello\n\
+        setblock 12 66 12 minecraft:observer [\"facing_direction\"=3, \"minecraft:block_face\"=\"up\"]\n\
+        fill 10 64 10 14 64 14 glass replace stone\n\
+        setblock 12 64 12 planks keep\n\
+        setblock 9 64 10 wool 14\n";
+    let s = McStructure::from_commands(src)?;
+    assert_eq!((s.size, s.structure_world_origin), ([6, 5, 5], [9, 64, 10]));
+    assert_eq!(s.get_block(1, 1, 0).unwrap().name, "minecraft:stone");
+    assert_eq!(s.get_block(2, 0, 1).unwrap().name, "minecraft:glass");
+    assert_eq!(s.get_block(2, 1, 1).unwrap().name, "minecraft:air");
+    let observer = s.get_block(3, 2, 2).unwrap();
+    assert_eq!(observer.states["minecraft:block_face"], Value::from("up"));
+    let wool = s.get_block(0, 0, 0).unwrap();
+    assert_eq!(
+        (wool.version, &wool.other["val"]),
+        (None, &Value::Short(14))
+    );
+    assert!(s.get_block(0, 1, 0).is_none());
+
+    let err = McStructure::from_commands("\nfill 0 0 0 1 1 stone").unwrap_err();
+    assert!(err.to_string().contains("第2行"));
+    assert!(McStructure::from_commands("setblock ^ ^ ^ stone").is_err());
+    //同一坐标轴混用相对坐标与绝对坐标
+    let err = McStructure::from_commands("setblock ~ 64 ~ stone\nsetblock 100 ~ 100 stone");
+    assert!(err.unwrap_err().to_string().contains("第2行"));
+    let s = McStructure::from_commands("setblock ~ 64 ~ stone\nsetblock ~1 65 ~ stone")?;
+    assert_eq!((s.size, s.structure_world_origin), ([2, 2, 1], [0, 64, 0]));
+    //超过32768个方块的fill
+    assert!(McStructure::from_commands("fill 0 0 0 32 31 31 stone").is_err());
+    assert!(McStructure::from_commands("fill 0 0 0 100000000 0 0 stone").is_err());
+    assert_eq!(
+        McStructure::from_commands("fill 0 0 0 31 31 31 stone")?.size,
+        [32, 32, 32]
+    );
+
+    let original = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let commands = original.to_commands(&ExportOptions::default()).join("\n");
+    let imported = McStructure::from_commands(&commands)?;
+    let (min, size) = original.bounding_box(false).unwrap();
+    assert_eq!(imported.size, size);
+    for index in 0..imported.volume() {
+        let [x, y, z] = imported.position(index);
+        let (a, b) = (
+            imported.get_block(x, y, z),
+            original.get_block(x + min[0], y + min[1], z + min[2]),
+        );
+        assert!(a.zip(b).is_none_or(|(a, b)| a.same_block(b)) && a.is_some() == b.is_some());
+    }
+    Ok(())
+}
+
+#[test]
+fn structure_split_and_join() -> IResult<()> {
+    use crate::structure::McStructure;
+    use crate::{nbt, Map};
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    s.entities.push(Map::try_from(
+        nbt!({ identifier: "minecraft:pig", Pos: [490.5f32, 115.0, 630.5] }),
+    )?);
+    let set = s.split([16, 32, 16]);
+    assert_eq!(set.tiles.len(), 4);
+    //与裁剪整个结构的结果相同
+    for tile in &set.tiles {
+        let mut cropped = s.clone();
+        cropped.crop(tile.offset, tile.structure.size)?;
+        cropped.compact_palette();
+        assert_eq!(tile.structure, cropped);
+    }
+    assert_eq!(set.tiles[3].structure.entities.len(), 1);
+    let last = set.tiles.last().unwrap();
+    assert_eq!(
+        (last.grid, last.offset, last.structure.size),
+        ([1, 1, 0], [16, 32, 0], [12, 18, 9])
+    );
+    let data = set
+        .tiles
+        .iter()
+        .map(|t| t.structure.block_position_data.len());
+    assert_eq!(data.sum::<usize>(), s.block_position_data.len());
+    assert!(set.tiles.iter().all(|t| t.structure.validate().is_empty()));
+
+    assert_eq!(
+        set.load_commands("farm", None)[1],
+        "structure load farm_0_1_0 ~ ~32 ~"
+    );
+    assert_eq!(
+        set.load_commands("farm", Some([0, -64, 0]))[3],
+        "structure load farm_1_1_0 16 -32 0"
+    );
+    let manifest = set.manifest("farm");
+    assert!(manifest.starts_with(
+        "{\"size\":[28,50,9],\"origin\":[467,79,625],\"tiles\":[{\"name\":\"farm_0_0_0\""
+    ));
+
+    let joined = set.join();
+    assert_eq!(
+        (joined.size, joined.structure_world_origin),
+        (s.size, s.structure_world_origin)
+    );
+    for index in 0..s.volume() {
+        let [x, y, z] = s.position(index);
+        let (a, b) = (s.get_block(x, y, z), joined.get_block(x, y, z));
+        assert!(a.zip(b).is_none_or(|(a, b)| a.same_block(b)) && a.is_some() == b.is_some());
+        assert_eq!(
+            s.block_position_data.get(&(index as i32)),
+            joined.block_position_data.get(&(index as i32))
+        );
+    }
+    assert_eq!(joined.entities.len(), s.entities.len());
+
+    //没有坐标的实体只分配到一个分块
+    s.entities.push(Map::try_from(
+        nbt!({ identifier: "minecraft:armor_stand" }),
+    )?);
+    let set = s.split([16, 32, 16]);
+    assert_eq!(set.tiles[0].structure.entities.len(), 1);
+    assert_eq!(set.join().entities.len(), s.entities.len());
+    Ok(())
+}
+
+#[test]
+fn structure_diff() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure};
+    use crate::{nbt, Map, Value};
+    let mut old = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    old.entities.push(Map::try_from(nbt!({
+        identifier: "minecraft:zombie_pigman",
+        Pos: [470.5f32, 80.0, 630.5],
+        UniqueID: -12884901887i64
+    }))?);
+    assert!(old.diff(&old).is_empty());
+
+    //调色板顺序与世界坐标不影响比较
+    let mut new = old.clone();
+    new.block_palette.reverse();
+    let len = new.block_palette.len() as i32;
+    for i in new.block_indices.iter_mut().flatten().filter(|i| **i >= 0) {
+        *i = len - 1 - *i;
+    }
+    new.structure_world_origin = [0, -64, 0];
+    let mut pairs = 0;
+    for data in new.block_position_data.values_mut() {
+        if let Some(Value::Compound(entity)) = data.get_mut("block_entity_data") {
+            entity.insert("y".to_owned(), Value::Int(0));
+            //大箱子另一半的世界坐标随原点移动
+            for (key, shift) in [("pairx", 467), ("pairz", 625)] {
+                if let Some(Value::Int(v)) = entity.get_mut(key) {
+                    *v -= shift;
+                    pairs += 1;
+                }
+            }
+        }
+    }
+    assert!(pairs > 0);
+    for entity in &mut new.entities {
+        entity.insert("UniqueID".to_owned(), Value::Long(0));
+        if let Some(Value::List(pos)) = entity.get_mut("Pos") {
+            for (p, shift) in pos.iter_mut().zip([467.0, 79.0 + 64.0, 625.0]) {
+                *p = Value::Float(p.to_f32().unwrap() - shift);
+            }
+        }
+    }
+    assert!(old.diff(&new).is_empty());
+
+    new.set_block(0, 0, 0, BlockState::new("minecraft:gold_block"))?;
+    new.remove_block(23, 1, 6)?;
+    new.entities.pop();
+    let mut data = new.block_position_data.clone();
+    let (&key, value) = data.iter_mut().next().unwrap();
+    value.insert("extra".to_owned(), Value::Byte(1));
+    new.block_position_data = data;
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.blocks[0].pos, [0, 0, 0]);
+    assert_eq!(
+        diff.blocks[0].after.as_ref().unwrap().name,
+        "minecraft:gold_block"
+    );
+    assert!(diff
+        .blocks
+        .iter()
+        .any(|d| d.pos == [23, 1, 6] && d.before.is_some() && d.after.is_none()));
+    assert_eq!(diff.block_data.len(), 2);
+    assert_eq!(
+        (diff.entities_added.len(), diff.entities_removed.len()),
+        (0, 1)
+    );
+
+    let patch = diff.to_structure(&new);
+    assert_eq!(
+        patch.get_block(0, 0, 0).unwrap().name,
+        "minecraft:gold_block"
+    );
+    assert!(patch.get_block(23, 1, 6).unwrap().is_air());
+    let [x, y, z] = new.position(key as usize);
+    assert_eq!(patch.get_block(x, y, z), new.get_block(x, y, z));
+    assert!(patch.get_block(1, 0, 0).is_none());
+    Ok(())
+}
+
+#[test]
+fn structure_upgrade_legacy() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure};
+    use crate::Value;
+    let mut s = McStructure::from_commands(
+        "setblock 0 0 0 wool 14\n\
+         setblock 1 0 0 oak_stairs 6\n\
+         setblock 2 0 0 log 9\n\
+         setblock 3 0 0 gold_block 3\n\
+         setblock 4 0 0 stone\n",
+    )?;
+    //与第一项相同的旧版本调色板项, 升级后成为重复项
+    let mut red = BlockState::new("minecraft:wool");
+    red.version = None;
+    red.other.insert("val".to_owned(), Value::Short(14));
+    s.block_palette.push(red);
+    let index = s.index(4, 0, 0).unwrap();
+    s.block_indices[0][index] = s.block_palette.len() as i32 - 1;
+
+    let report = s.upgrade_legacy();
+    assert_eq!(report.upgraded, 4);
+    assert_eq!(report.unknown, vec![("minecraft:gold_block".to_owned(), 3)]);
+    let wool = s.get_block(0, 0, 0).unwrap();
+    assert_eq!(wool.states["color"], Value::from("red"));
+    assert_eq!(wool.version, Some(BlockState::LEGACY_UPGRADE_VERSION));
+    assert!(!wool.is_legacy());
+    let stairs = &s.get_block(1, 0, 0).unwrap().states;
+    assert_eq!(
+        (&stairs["weirdo_direction"], &stairs["upside_down_bit"]),
+        (&Value::Int(2), &Value::Byte(1))
+    );
+    let log = &s.get_block(2, 0, 0).unwrap().states;
+    assert_eq!(
+        (&log["old_log_type"], &log["pillar_axis"]),
+        (&Value::from("spruce"), &Value::from("z"))
+    );
+    assert!(s.get_block(3, 0, 0).unwrap().is_legacy());
+
+    let report = s.compact_palette();
+    assert_eq!((report.unused, report.duplicates), (1, 1));
+    assert_eq!(s.block_palette.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn structure_block_entities() -> IResult<()> {
+    use crate::structure::{
+        BlockEntity, BlockEntityKind, CommandBlock, ItemStack, McStructure, SignText,
+    };
+    use crate::{nbt, NbtValue, Value};
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    for data in s.block_position_data.values() {
+        if let Some(v) = data.get("block_entity_data") {
+            assert_eq!(&BlockEntity::from_value(v.clone())?.to_value(), v);
+        }
+    }
+
+    let mut sign = s.block_entity(23, 1, 6)?.unwrap();
+    assert_eq!(sign.pos, [490, 80, 631]);
+    let BlockEntityKind::Sign(text) = &mut sign.kind else {
+        panic!("{:?}", sign.kind)
+    };
+    text.front.text = "金农场".to_owned();
+    text.front.sign_text_color = 0xffff0000_u32 as i32;
+    s.set_block_entity(23, 1, 6, sign)?;
+    assert_eq!(
+        s.block_position_data[&10365]["block_entity_data"]["FrontText"]["Text"],
+        Value::from("金农场")
+    );
+
+    let (x, y, z) = (0..s.volume())
+        .map(|i| s.position(i))
+        .find_map(|[x, y, z]| match s.block_entity(x, y, z) {
+            Ok(Some(e)) if e.id == "Chest" => Some((x, y, z)),
+            _ => None,
+        })
+        .unwrap();
+    let mut chest = s.block_entity(x, y, z)?.unwrap();
+    assert!(matches!(&chest.kind, BlockEntityKind::Chest(c) if c.pair.is_some()));
+    let mut diamond = ItemStack::new("minecraft:diamond", 1);
+    diamond.slot = Some(0);
+    chest.items_mut().unwrap().push(diamond);
+    s.set_block_entity(x, y, z, chest)?;
+    assert_eq!(s.block_entity(x, y, z)?.unwrap().items().unwrap().len(), 1);
+
+    //旧版本的单面告示牌
+    let legacy =
+        nbt!({ Text: "hi", TextOwner: "", extra: 1, id: "Sign", isMovable: 1i8, x: 0, y: 0, z: 0 });
+    let sign = BlockEntity::from_value(legacy.clone())?;
+    let BlockEntityKind::Sign(text) = &sign.kind else {
+        panic!()
+    };
+    assert_eq!(
+        (text.front.text.as_str(), &text.back),
+        ("hi", &None::<SignText>)
+    );
+    assert_eq!(sign.other["extra"], Value::Int(1));
+    assert!(sign.to_value().get("FrontText").is_none());
+    assert_eq!(sign.to_value(), legacy);
+    //只有`FrontText`的告示牌仍以新格式写回
+    let front = nbt!({ FrontText: { Text: "hi" }, IsWaxed: 0i8, id: "Sign", x: 0, y: 0, z: 0 });
+    assert_eq!(BlockEntity::from_value(front.clone())?.to_value(), front);
+
+    let mut block = BlockEntity::new("CommandBlock");
+    block.kind = BlockEntityKind::CommandBlock(CommandBlock {
+        command: "say hi".to_owned(),
+        auto: true,
+        ..Default::default()
+    });
+    let v = block.to_value();
+    assert_eq!(
+        (v["Command"].as_str(), v["auto"].as_bool()),
+        (Some("say hi"), Some(true))
+    );
+    assert!(BlockEntity::from_value(nbt!({ id: "Hopper", Items: 1 })).is_err());
+    Ok(())
+}
+
+#[test]
+fn structure_item_stack() -> IResult<()> {
+    use crate::structure::{Enchantment, ItemStack};
+    use crate::{nbt, NbtValue, Value};
+    let v = nbt!({
+        Block: { name: "minecraft:shulker_box", states: { color: "red" }, version: 17959425 },
+        Count: 1i8,
+        Damage: 14i16,
+        Name: "minecraft:shulker_box",
+        Slot: 3i8,
+        WasPickedUp: 0i8,
+        tag: {
+            Items: [{ Count: 64i8, Damage: 0i16, Name: "minecraft:stone", Slot: 0i8, WasPickedUp: 0i8 }],
+            display: { Name: "仓库" },
+            ench: [{ id: 9i16, lvl: 5i16 }]
+        }
+    });
+    let mut item = ItemStack::from_value(v.clone())?;
+    assert_eq!(item.to_value(), v);
+    assert_eq!(item.custom_name(), Some("仓库"));
+    assert_eq!(item.enchantments(), [Enchantment { id: 9, lvl: 5 }]);
+    assert_eq!(item.contents()?[0].count, 64);
+
+    item.set_enchantment(9, 3);
+    item.set_enchantment(17, 1);
+    assert_eq!(item.enchantments().len(), 2);
+    assert!(item.remove_enchantment(9) && !item.remove_enchantment(9));
+    item.set_lore(&["第一行", "第二行"]);
+    assert_eq!(item.lore(), ["第一行", "第二行"]);
+    item.set_contents(&[]);
+    assert!(item.contents()?.is_empty());
+
+    item.set_lore::<&str>(&[]);
+    item.set_custom_name(None);
+    item.remove_enchantment(17);
+    assert_eq!(item.tag, None);
+
+    //没有`Damage`与`WasPickedUp`的物品写回时也不会补全
+    let v = nbt!({ Count: 1i8, Name: "minecraft:apple" });
+    let mut item = ItemStack::from_value(v.clone())?;
+    assert_eq!((item.damage, item.to_value()), (0, v));
+    item.damage = 3;
+    assert_eq!(item.to_value()["Damage"], Value::Short(3));
+    Ok(())
+}
+
+#[test]
+fn structure_entities() -> IResult<()> {
+    use crate::structure::{Entity, ItemStack, McStructure};
+    use crate::{nbt, NbtValue, Value};
+    let v = nbt!({
+        Armor: [
+            { Count: 0i8, Damage: 0i16, Name: "", WasPickedUp: 0i8 },
+            { Count: 0i8, Damage: 0i16, Name: "", WasPickedUp: 0i8 },
+            { Count: 0i8, Damage: 0i16, Name: "", WasPickedUp: 0i8 },
+            { Count: 0i8, Damage: 0i16, Name: "", WasPickedUp: 0i8 }
+        ],
+        Attributes: [{
+            Base: 20.0f32, Current: 20.0f32, DefaultMax: 20.0f32, DefaultMin: 0.0f32,
+            Max: 20.0f32, Min: 0.0f32, Name: "minecraft:health"
+        }],
+        Mainhand: [{ Count: 1i8, Damage: 0i16, Name: "minecraft:golden_sword", WasPickedUp: 0i8 }],
+        Motion: [0.0f32, 0.0, 0.0],
+        Pos: [470.5f32, 80.0, 630.5],
+        Rotation: [90.0f32, 0.0],
+        UniqueID: -12884901887i64,
+        definitions: ["+minecraft:zombie_pigman"],
+        identifier: "minecraft:zombie_pigman",
+        internalComponents: {}
+    });
+    let mut entity = Entity::from_value(v.clone())?;
+    assert_eq!(entity.to_value(), v);
+    assert_eq!(entity.attribute("minecraft:health").unwrap().current, 20.0);
+    assert!(entity.armor.iter().all(ItemStack::is_empty));
+    //缺少的键不会补全, 空的列表原样保留
+    let sparse = nbt!({ Armor: [], Attributes: [], identifier: "minecraft:armor_stand" });
+    assert_eq!(Entity::from_value(sparse.clone())?.to_value(), sparse);
+    let fresh = Entity::new("minecraft:pig").to_value();
+    assert!(fresh.get("Armor").is_none() && fresh.get("Pos").is_some());
+    entity.translate([-467.0, -79.0, -625.0]);
+    assert_eq!(entity.pos, [3.5, 1.0, 5.5]);
+
+    let mut s = McStructure::new([8, 8, 8]);
+    s.add_entity(&entity);
+    let mut rider = Entity::new("minecraft:zombie");
+    rider.unique_id = -2;
+    rider.other.insert(
+        "LinksTag".to_owned(),
+        nbt!([{ entityID: -12884901887i64, linkID: 0 }]),
+    );
+    rider
+        .other
+        .insert("LeasherID".to_owned(), Value::Long(-12884901887));
+    rider
+        .other
+        .insert("OwnerNew".to_owned(), Value::Long(-12884901887));
+    s.add_entity(&rider);
+    s.regenerate_unique_ids();
+    let (a, b) = (s.entity(0)?.unwrap(), s.entity(1)?.unwrap());
+    assert!(a.unique_id != -12884901887 && a.unique_id != b.unique_id);
+    assert_eq!(b.other["LinksTag"][0]["entityID"], Value::Long(a.unique_id));
+    assert_eq!(b.other["LeasherID"], Value::Long(a.unique_id));
+    assert_eq!(b.other["OwnerNew"], Value::Long(a.unique_id));
+    assert!(s.entity(2)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn structure_block_state_schema() -> IResult<()> {
+    use crate::structure::{BlockState, McStructure, Problem, StateType};
+    use crate::Value;
+    use std::collections::HashSet;
+    let mut s = McStructure::read(&mut std::fs::File::open("res/gold_farm.mcstructure")?)?;
+    let palette = s.block_palette.clone();
+    assert_eq!(s.normalize_states(), 0);
+    assert_eq!(s.block_palette, palette);
+    for block in &palette {
+        let parsed: BlockState = block.to_string().parse()?;
+        assert_eq!(parsed.states, block.states);
+    }
+
+    let a: BlockState = "minecraft:hopper[toggle_bit=false,facing_direction=\"3\"]".parse()?;
+    let b: BlockState = "hopper[facing_direction=3,toggle_bit=0]".parse()?;
+    assert_eq!(a, b);
+    assert_eq!(a.states["facing_direction"], Value::Int(3));
+    //状态的顺序不影响比较与哈希
+    let mut c = BlockState::new("minecraft:hopper");
+    c.states.insert("toggle_bit".to_owned(), Value::Byte(0));
+    c.states
+        .insert("facing_direction".to_owned(), Value::Int(3));
+    assert_eq!(HashSet::from([a.clone(), b, c]).len(), 1);
+
+    //引号中的`,`, `]`与`=`不分隔状态, 写出时会重新加上引号
+    let sign: BlockState = r#"standing_sign[text="a,b]=c",kind=" \"q\" "]"#.parse()?;
+    assert_eq!(sign.states["text"], Value::from("a,b]=c"));
+    assert_eq!(sign.states["kind"], Value::from(r#" "q" "#));
+    assert_eq!(sign.to_string().parse::<BlockState>()?, sign);
+    let number: BlockState = "stone[stone_type=\"1\"]".parse()?;
+    assert_eq!(number.to_string(), "minecraft:stone[stone_type=\"1\"]");
+    assert_eq!(StateType::of("open_bit"), Some(StateType::Byte));
+    assert_eq!(StateType::of("unknown_state"), None);
+
+    let mut wrong = BlockState::new("minecraft:trapdoor");
+    wrong.states.insert("open_bit".to_owned(), Value::Int(1));
+    wrong
+        .states
+        .insert("direction".to_owned(), Value::String("x".to_owned()));
+    s.block_palette.push(wrong);
+    let issues = s.validate();
+    assert_eq!(issues.len(), 2);
+    assert!(matches!(
+        &issues[0].problem,
+        Problem::StateValue {
+            expected: StateType::Byte,
+            found: "TAG_Int",
+            ..
+        }
+    ));
+    //"x"无法转换为整数, 不会被修复
+    assert_eq!(s.repair().len(), 1);
+    assert_eq!(
+        s.block_palette.last().unwrap().states["open_bit"],
+        Value::Byte(1)
+    );
+    Ok(())
+}
+
+#[test]
+fn structure_command_blocks() -> IResult<()> {
+    use crate::structure::{BlockEntity, BlockEntityKind, CommandBlockMode, McStructure};
+    use crate::{nbt, Value};
+    let mut s = McStructure::new([3, 1, 1]);
+    s.structure_world_origin = [100, 64, 100];
+    for (x, (name, command)) in [
+        (
+            "repeating_command_block[conditional_bit=false]",
+            "testfor @p[x=100,y=64,z=100,r=5]",
+        ),
+        ("chain_command_block[conditional_bit=true]", "say @p"),
+        ("command_block", "tp @s 100 64 100"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        s.set_block(x as i32, 0, 0, name.parse()?)?;
+        let mut block = BlockEntity::new("CommandBlock");
+        if let BlockEntityKind::CommandBlock(c) = &mut block.kind {
+            c.command = command.to_owned();
+            c.auto = x < 2;
+        }
+        s.set_block_entity(x as i32, 0, 0, block)?;
+    }
+    //缺少大部分键的命令方块, 以及超出结构范围的下标
+    let raw = nbt!({ Command: "tp @s 100 64 100", Version: 36, id: "CommandBlock", x: 102, y: 64, z: 100 });
+    let data = s.block_position_data.get_mut(&2).unwrap();
+    data.insert("block_entity_data".to_owned(), raw.clone());
+    let outside = s.block_position_data[&2].clone();
+    s.block_position_data.insert(3, outside);
+
+    let list = s.command_blocks();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[1].mode, Some(CommandBlockMode::Chain));
+    assert!(list[1].conditional && list[1].auto && !list[2].auto);
+
+    //将坐标平移到新的位置
+    let n = s.rewrite_commands(|b| {
+        b.command
+            .contains("100")
+            .then(|| b.command.replace("100", "200"))
+    });
+    assert_eq!(n, 2);
+    let list = s.command_blocks();
+    assert_eq!(list[0].command, "testfor @p[x=200,y=64,z=200,r=5]");
+    assert_eq!(list[1].command, "say @p");
+    let mut expected = raw;
+    expected["Command"] = Value::from("tp @s 200 64 200");
+    assert_eq!(s.block_position_data[&2]["block_entity_data"], expected);
+    assert_eq!(s.block_entity(2, 0, 0)?.unwrap().pos, [102, 64, 100]);
+    Ok(())
+}
+
+#[test]
+fn world_command_blocks() -> IResult<()> {
+    use crate::nbt;
+    use crate::structure::{rewrite_world_commands, world_command_blocks};
+    let records = [
+        nbt!({ Command: "say 1", conditionalMode: true, id: "CommandBlock", x: 10, y: 64, z: -3 }),
+        nbt!({ Items: [], id: "Chest", x: 11, y: 64, z: -3 }),
+        //类型错误的命令方块会被跳过
+        nbt!({ Command: 1, id: "CommandBlock", x: 12, y: 64, z: -3 }),
+        nbt!({ Command: "say 1", id: "CommandBlock", x: 13, y: 64, z: -3 }),
+    ];
+    let mut bytes = vec![];
+    for data in records {
+        NBT {
+            name: String::new(),
+            data,
+        }
+        .write(&mut bytes, false)?;
+    }
+    let mut records = NBT::read_all(&bytes)?;
+    assert_eq!(records.len(), 4);
+    let list = world_command_blocks(&records);
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[1].pos, [13, 64, -3]);
+    assert!(list[0].conditional && list[0].mode.is_none());
+
+    let n = rewrite_world_commands(&mut records, |b| Some(b.command.replace('1', "2")));
+    assert_eq!(n, 2);
+    assert_eq!(records[3].data["Command"], "say 2".into());
+    assert_eq!(records[2].data["Command"], 1.into());
     Ok(())
 }
@@ -0,0 +1,145 @@
+//! 遍历[`Value`]树
+//!
+//! 路径以`/`分隔, `TAG_List`中的元素以下标作为路径, 与[`Value::get_path`]一致,
+//! 根标签的路径为空字符串
+//!
+//! # Example
+//!
+//! 删除所有的`isMovable`键
+//!
+//! ```
+//! use nbtrock::{nbt, visit::{walk_compound_mut, VisitMut}, Map, Value};
+//!
+//! struct Strip(&'static str);
+//!
+//! impl VisitMut for Strip {
+//!     fn visit_compound_mut(&mut self, path: &str, map: &mut Map<String, Value>) {
+//!         map.remove(self.0);
+//!         walk_compound_mut(self, path, map);
+//!     }
+//! }
+//!
+//! let mut v = nbt!({ "isMovable": 1i8, "data": [{ "isMovable": 0i8, "id": "Chest" }] });
+//! v.visit_mut(&mut Strip("isMovable"));
+//! assert_eq!(v, nbt!({ "data": [{ "id": "Chest" }] }));
+//! ```
+use crate::{join_path, Map, Value};
+
+///以只读方式遍历[`Value`], 每个方法的默认实现会继续访问子标签
+pub trait Visit {
+    fn visit_value(&mut self, path: &str, value: &Value) {
+        walk_value(self, path, value)
+    }
+
+    fn visit_compound(&mut self, path: &str, map: &Map<String, Value>) {
+        walk_compound(self, path, map)
+    }
+
+    fn visit_list(&mut self, path: &str, list: &[Value]) {
+        walk_list(self, path, list)
+    }
+}
+
+///以可变方式遍历[`Value`], 每个方法的默认实现会继续访问子标签
+pub trait VisitMut {
+    fn visit_value_mut(&mut self, path: &str, value: &mut Value) {
+        walk_value_mut(self, path, value)
+    }
+
+    fn visit_compound_mut(&mut self, path: &str, map: &mut Map<String, Value>) {
+        walk_compound_mut(self, path, map)
+    }
+
+    fn visit_list_mut(&mut self, path: &str, list: &mut Vec<Value>) {
+        walk_list_mut(self, path, list)
+    }
+}
+
+pub fn walk_value<V: Visit + ?Sized>(visitor: &mut V, path: &str, value: &Value) {
+    match value {
+        Value::Compound(map) => visitor.visit_compound(path, map),
+        Value::List(list) => visitor.visit_list(path, list),
+        _ => {}
+    }
+}
+
+pub fn walk_compound<V: Visit + ?Sized>(visitor: &mut V, path: &str, map: &Map<String, Value>) {
+    for (key, value) in map {
+        visitor.visit_value(&join_path(path, key), value);
+    }
+}
+
+pub fn walk_list<V: Visit + ?Sized>(visitor: &mut V, path: &str, list: &[Value]) {
+    for (index, value) in list.iter().enumerate() {
+        visitor.visit_value(&join_path(path, &index.to_string()), value);
+    }
+}
+
+pub fn walk_value_mut<V: VisitMut + ?Sized>(visitor: &mut V, path: &str, value: &mut Value) {
+    match value {
+        Value::Compound(map) => visitor.visit_compound_mut(path, map),
+        Value::List(list) => visitor.visit_list_mut(path, list),
+        _ => {}
+    }
+}
+
+pub fn walk_compound_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    path: &str,
+    map: &mut Map<String, Value>,
+) {
+    for (key, value) in map.iter_mut() {
+        visitor.visit_value_mut(&join_path(path, key), value);
+    }
+}
+
+pub fn walk_list_mut<V: VisitMut + ?Sized>(visitor: &mut V, path: &str, list: &mut [Value]) {
+    for (index, value) in list.iter_mut().enumerate() {
+        visitor.visit_value_mut(&join_path(path, &index.to_string()), value);
+    }
+}
+
+///深度优先遍历所有标签的迭代器, 由[`Value::walk`]创建
+pub struct Walk<'a> {
+    stack: Vec<(String, &'a Value)>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, value) = self.stack.pop()?;
+        match value {
+            Value::Compound(map) => self.stack.extend(
+                map.iter()
+                    .rev()
+                    .map(|(key, value)| (join_path(&path, key), value)),
+            ),
+            Value::List(list) => self.stack.extend(
+                list.iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, value)| (join_path(&path, &index.to_string()), value)),
+            ),
+            _ => {}
+        }
+        Some((path, value))
+    }
+}
+
+impl Value {
+    ///以先序深度优先的顺序遍历自身及所有子标签, 返回`(路径, 标签)`
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![(String::new(), self)],
+        }
+    }
+
+    pub fn visit<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_value("", self)
+    }
+
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_value_mut("", self)
+    }
+}
@@ -0,0 +1,146 @@
+use nbtrock::{nbt, Error, IResult, Map, NbtValue, Value};
+use nbtrock_derive::NbtCompound;
+
+#[derive(NbtCompound, Debug, PartialEq)]
+struct Item {
+    #[nbt(rename = "Name")]
+    name: String,
+    #[nbt(rename = "Count")]
+    count: i8,
+    #[nbt(rename = "Damage", default)]
+    damage: i16,
+    #[nbt(rename = "WasPickedUp", byte)]
+    picked_up: bool,
+    #[nbt(rename = "Slot", byte)]
+    slot: Option<i8>,
+    #[nbt(unknown)]
+    other: Map<String, Value>,
+}
+
+#[derive(NbtCompound, Debug, PartialEq)]
+struct Position {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+#[derive(NbtCompound, Debug, PartialEq)]
+struct Container {
+    id: String,
+    #[nbt(flatten)]
+    pos: Position,
+    #[nbt(rename = "Items")]
+    items: Vec<Item>,
+    #[nbt(rename = "Heights", int_array, default = "Vec::new")]
+    heights: Vec<i32>,
+}
+
+#[test]
+fn derive_round_trip() -> IResult<()> {
+    let value = nbt!({
+        "Items": [{
+            "Count": 1i8,
+            "Name": "minecraft:water_bucket",
+            "Slot": 3i8,
+            "WasPickedUp": 0i8,
+            "tag": { "Damage": 0 },
+        }],
+        "id": "Dispenser",
+        "x": 490,
+        "y": 112,
+        "z": 627,
+        "Heights": [I; 1, 2],
+    });
+    let container = Container::from_value(value)?;
+    assert_eq!(
+        container.pos,
+        Position {
+            x: 490,
+            y: 112,
+            z: 627
+        }
+    );
+    assert_eq!(container.heights, vec![1, 2]);
+    let item = &container.items[0];
+    assert_eq!(item.slot, Some(3));
+    assert_eq!(item.damage, 0);
+    assert!(!item.picked_up);
+    assert_eq!(item.other["tag"], nbt!({ "Damage": 0 }));
+
+    let written = container.to_value();
+    assert_eq!(Container::from_value(written.clone())?, container);
+    if let Value::Compound(map) = written {
+        assert_eq!(map["Heights"], Value::IntArray(vec![1, 2]));
+        assert_eq!(
+            map["Items"],
+            nbt!([{
+                "Name": "minecraft:water_bucket",
+                "Count": 1i8,
+                "Damage": 0i16,
+                "WasPickedUp": 0i8,
+                "Slot": 3i8,
+                "tag": { "Damage": 0 },
+            }])
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn derive_errors() {
+    match Position::from_value(nbt!({ "x": 1, "y": 2 })) {
+        Err(Error::MissingKey(key)) => assert_eq!(key, "z"),
+        r => panic!("{r:?}"),
+    }
+    match Container::from_value(nbt!({
+        "id": "Chest", "x": 0, "y": 0, "z": 0,
+        "Items": [{ "Name": "minecraft:stone", "Count": 1i16, "WasPickedUp": 0i8 }],
+    })) {
+        Err(Error::Type {
+            expected,
+            found,
+            path,
+        }) => {
+            assert_eq!((expected, found), ("TAG_Byte", "TAG_Short"));
+            assert_eq!(path, "Items/0/Count");
+        }
+        r => panic!("{r:?}"),
+    }
+}
+
+#[derive(NbtCompound, Debug, PartialEq)]
+struct Tagged {
+    r#type: String,
+    #[nbt(int)]
+    small: i8,
+    #[nbt(double)]
+    ratio: f32,
+    #[nbt(long_array)]
+    ids: Vec<i32>,
+}
+
+#[test]
+fn derive_tag_range() -> IResult<()> {
+    let value = nbt!({ "type": "a", "small": 1, "ratio": 0.5f64, "ids": [L; 1, 2] });
+    let tagged = Tagged::from_value(value.clone())?;
+    assert_eq!(
+        (tagged.small, tagged.ratio, &tagged.ids),
+        (1, 0.5, &vec![1, 2])
+    );
+    assert_eq!(tagged.to_value(), value);
+    match Tagged::from_value(nbt!({ "type": "a", "small": 300, "ratio": 0.0, "ids": [L;] })) {
+        Err(Error::Range { expected, path, .. }) => {
+            assert_eq!((expected, path.as_str()), ("TAG_Byte", "small"))
+        }
+        r => panic!("{r:?}"),
+    }
+    match Tagged::from_value(
+        nbt!({ "type": "a", "small": 0, "ratio": 0.0, "ids": [L; 1, 1 << 40] }),
+    ) {
+        Err(Error::Range { expected, path, .. }) => {
+            assert_eq!((expected, path.as_str()), ("TAG_Int", "ids/1"))
+        }
+        r => panic!("{r:?}"),
+    }
+    Ok(())
+}
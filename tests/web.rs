@@ -1,3 +1,4 @@
+#![cfg(target_arch = "wasm32")]
 use nbtrock::{wasm, IResult, NBT};
 use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
 